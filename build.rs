@@ -1,8 +1,15 @@
+extern crate alloc;
+
 use std::{env, fmt::Write, fs, path::Path};
 
 #[path = "src/int.rs"]
 mod int;
-use self::int::BinInteger as _;
+
+#[path = "src/glyphindex.rs"]
+#[allow(dead_code)]
+mod glyphindex;
+
+use glyphindex::GlyphMetric;
 
 fn main() {
     let mut glyphsets_rs = String::new();
@@ -15,6 +22,7 @@ fn main() {
     let dest_path = Path::new(&out_dir).join("glyphsets.rs");
     fs::write(&dest_path, glyphsets_rs).unwrap();
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/glyphindex.rs");
 }
 
 fn process_one_glyph_set(glyphsets_rs: &mut String, gs: &GlyphSetIn) {
@@ -22,94 +30,12 @@ fn process_one_glyph_set(glyphsets_rs: &mut String, gs: &GlyphSetIn) {
         ($($tt:tt)*) => { writeln!(glyphsets_rs, $($tt)*).unwrap() };
     }
 
-    let mask_dims = gs.mask_dims;
-
-    #[derive(Clone, Copy)]
-    struct IndexEnt {
-        glyph_i: usize,
-        distance: usize,
-    }
-    let mut index = vec![None; 1 << (mask_dims[0] * mask_dims[1])];
-
-    // Put the exact matches
-    for (glyph_i, &(_, mask)) in gs.glyphs.iter().enumerate() {
-        let mask = decode_mask(mask, mask_dims);
-        if index[mask as usize].is_some() {
-            // If there are two glyphs with identical masks, the first one
-            // takes precedence
-            continue;
-        }
-        index[mask as usize] = Some(IndexEnt {
-            glyph_i,
-            distance: 0,
-        });
-    }
-
-    // Mutate known patterns to create lesser matches
-    let mut last_distance = 0;
-    for base_distance in 0.. {
-        let mut should_continue = false;
-
-        for mask in 0..index.len() {
-            if let Some(IndexEnt { distance, glyph_i }) = index[mask] {
-                if distance != base_distance {
-                    // Already processed by a previous iteration
-                    continue;
-                }
-
-                mutate_fragment_by_dilation_and_erosion(mask_dims, mask as Fragment, |new_mask| {
-                    if index[new_mask as usize].is_some() {
-                        return;
-                    }
-
-                    index[new_mask as usize] = Some(IndexEnt {
-                        glyph_i,
-                        distance: base_distance + 1,
-                    });
-                    eprintln!("{:09b} -> {:09b}, {}", mask, new_mask, base_distance + 1);
-                    should_continue = true;
-                });
-            }
-        }
-
-        if !should_continue {
-            break;
-        }
-
-        last_distance = base_distance;
-    }
-
-    // The above mutation technique doesn't cover the entire space. Now bring a
-    // bigger gun
-    for base_distance in 0.. {
-        let mut should_continue = false;
-
-        for mask in 0..index.len() {
-            if let Some(IndexEnt { distance, glyph_i }) = index[mask] {
-                if distance != base_distance {
-                    // Already processed by a previous iteration
-                    continue;
-                }
-
-                mutate_fragment_unconditional(mask_dims, mask as Fragment, |new_mask| {
-                    if index[new_mask as usize].is_some() {
-                        return;
-                    }
-
-                    index[new_mask as usize] = Some(IndexEnt {
-                        glyph_i,
-                        distance: base_distance + 1,
-                    });
-                    eprintln!("{:09b} -> {:09b}, {}", mask, new_mask, base_distance + 1);
-                    should_continue = true;
-                });
-            }
-        }
-
-        if !should_continue && base_distance > last_distance {
-            break;
-        }
-    }
+    let masks: Vec<Fragment> = gs
+        .glyphs
+        .iter()
+        .map(|&(_, mask)| decode_mask(mask, gs.mask_dims))
+        .collect();
+    let index = glyphindex::fill_index(gs.mask_dims, &masks, gs.metric);
 
     let max_glyph_len = gs.glyphs.iter().map(|x| x.0.len()).max().unwrap();
 
@@ -126,74 +52,34 @@ fn process_one_glyph_set(glyphsets_rs: &mut String, gs: &GlyphSetIn) {
         gs.mask_overlap[1]
     );
     wl!("        max_glyph_len: {},", max_glyph_len);
+    wl!("        glyph_width: {},", gs.glyph_width);
     wl!("        index: &[");
-    for ent in index.iter() {
-        wl!(
-            "            r##\"{}\"##,",
-            gs.glyphs[ent.unwrap().glyph_i].0
-        );
+    for chunk in index.chunks(16) {
+        let chunk: Vec<String> = chunk.iter().map(|i| i.to_string()).collect();
+        wl!("            {},", chunk.join(", "));
     }
     wl!("        ],");
-    wl!("    }};");
-}
-
-/// Mutate a fragment.
-fn mutate_fragment_by_dilation_and_erosion(
-    [w, h]: [usize; 2],
-    frag: Fragment,
-    mut cb: impl FnMut(Fragment),
-) {
-    let mut i = 0;
-    for y in 0..h {
-        for x in 0..w {
-            let cur = frag.get_bit(i);
-
-            // Deny dilation (the outcome is opposite - the result image will
-            // be dilated)
-            if cur {
-                continue;
-            }
-
-            // try copying this pixel to a neighboring one
-            for &(sx, sy) in &[(-1isize, 0), (1, 0), (0, -1isize), (0, 1)] {
-                let nx = x.wrapping_add(sx as usize);
-                let ny = y.wrapping_add(sy as usize);
-                if nx >= w || ny >= h {
-                    continue; // OOB
-                }
-
-                let ni = (nx + ny * w) as u32;
-                let nei = frag.get_bit(ni);
-
-                if cur == nei {
-                    continue; // no change
-                }
-
-                // mutate
-                let new_frag = frag ^ (1 << ni);
-
-                cb(new_frag);
-            }
-
-            i += 1;
-        }
-    }
-}
-
-/// Mutate a fragment differently.
-fn mutate_fragment_unconditional([w, h]: [usize; 2], frag: Fragment, mut cb: impl FnMut(Fragment)) {
-    for i in 0..w * h {
-        cb(frag & !(1 << i));
+    wl!("        glyphs: &[");
+    for (&(glyph, _), &mask) in gs.glyphs.iter().zip(masks.iter()) {
+        wl!("            (r##\"{}\"##, {:#x}),", glyph, mask);
     }
+    wl!("        ],");
+    wl!("    }};");
 }
 
 const GLYPH_SETS: &[&GlyphSetIn] = &[
     &GLYPH_SET_SLC,
     &GLYPH_SET_MS_2X3,
     &GLYPH_SET_1X1,
+    &GLYPH_SET_1X1_WIDE,
     &GLYPH_SET_1X2,
     &GLYPH_SET_2X2,
     &GLYPH_SET_2X3,
+    &GLYPH_SET_OCTANT,
+    &GLYPH_SET_LINE,
+    &GLYPH_SET_LINE_HEAVY,
+    &GLYPH_SET_LINE_DOUBLE,
+    &GLYPH_SET_ASCII,
 ];
 
 /// A small bitmap image, whose dimensions are specified implciitly (e.g., by
@@ -204,6 +90,10 @@ struct GlyphSetIn {
     const_name: &'static str,
     mask_dims: [usize; 2],
     mask_overlap: [usize; 2],
+    /// The number of display columns occupied by each glyph
+    glyph_width: usize,
+    /// How to choose glyphs for the fragments without exact matches
+    metric: GlyphMetric,
     glyphs: &'static [(&'static str, Fragment)],
 }
 
@@ -218,6 +108,8 @@ const GLYPH_SET_SLC: GlyphSetIn = GlyphSetIn {
     const_name: "GLYPH_SET_SLC",
     mask_dims: [3, 3],
     mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
     glyphs: &[
         (" ", 0b000_000_000),
         ("╋", 0b010_111_010),
@@ -307,6 +199,8 @@ const GLYPH_SET_MS_2X3: GlyphSetIn = GlyphSetIn {
     const_name: "GLYPH_SET_MS_2X3",
     mask_dims: [2, 3],
     mask_overlap: [1, 1],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
     glyphs: &[
         (" ", 0b00_00_00),
         ("█", 0b11_11_11),
@@ -414,13 +308,27 @@ const GLYPH_SET_1X1: GlyphSetIn = GlyphSetIn {
     const_name: "GLYPH_SET_1X1",
     mask_dims: [1, 1],
     mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
     glyphs: &[("█", 0b1), (" ", 0b0)],
 };
 
+/// Each glyph is two columns wide, making pixels roughly square
+const GLYPH_SET_1X1_WIDE: GlyphSetIn = GlyphSetIn {
+    const_name: "GLYPH_SET_1X1_WIDE",
+    mask_dims: [1, 1],
+    mask_overlap: [0, 0],
+    glyph_width: 2,
+    metric: GlyphMetric::Mutation,
+    glyphs: &[("██", 0b1), ("  ", 0b0)],
+};
+
 const GLYPH_SET_1X2: GlyphSetIn = GlyphSetIn {
     const_name: "GLYPH_SET_1X2",
     mask_dims: [1, 2],
     mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
     glyphs: &[("█", 0b1_1), (" ", 0b0_0), ("▀", 0b1_0), ("▄", 0b0_1)],
 };
 
@@ -428,6 +336,8 @@ const GLYPH_SET_2X2: GlyphSetIn = GlyphSetIn {
     const_name: "GLYPH_SET_2X2",
     mask_dims: [2, 2],
     mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
     glyphs: &[
         ("█", 0b11_11),
         ("▖", 0b00_10),
@@ -452,6 +362,8 @@ const GLYPH_SET_2X3: GlyphSetIn = GlyphSetIn {
     const_name: "GLYPH_SET_2X3",
     mask_dims: [2, 3],
     mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
     glyphs: &[
         ("█", 0b11_11_11),
         (" ", 0b00_00_00),
@@ -519,3 +431,429 @@ const GLYPH_SET_2X3: GlyphSetIn = GlyphSetIn {
         ("🬻", 0b01_11_11),
     ],
 };
+
+/// Block octants (Unicode 16). The octants that duplicate existing characters
+/// are not encoded in the octant range and are substituted with those
+/// characters (quadrants, halves, and one/three quarter blocks).
+const GLYPH_SET_OCTANT: GlyphSetIn = GlyphSetIn {
+    const_name: "GLYPH_SET_OCTANT",
+    mask_dims: [2, 4],
+    mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
+    glyphs: &[
+        ("█", 0b11_11_11_11),
+        (" ", 0b00_00_00_00),
+        ("𜺨", 0b10_00_00_00),
+        ("𜺫", 0b01_00_00_00),
+        ("🮂", 0b11_00_00_00),
+        ("𜴀", 0b00_10_00_00),
+        ("▘", 0b10_10_00_00),
+        ("𜴁", 0b01_10_00_00),
+        ("𜴂", 0b11_10_00_00),
+        ("𜴃", 0b00_01_00_00),
+        ("𜴄", 0b10_01_00_00),
+        ("▝", 0b01_01_00_00),
+        ("𜴅", 0b11_01_00_00),
+        ("𜴆", 0b00_11_00_00),
+        ("𜴇", 0b10_11_00_00),
+        ("𜴈", 0b01_11_00_00),
+        ("▀", 0b11_11_00_00),
+        ("𜴉", 0b00_00_10_00),
+        ("𜴊", 0b10_00_10_00),
+        ("𜴋", 0b01_00_10_00),
+        ("𜴌", 0b11_00_10_00),
+        ("🯦", 0b00_10_10_00),
+        ("𜴍", 0b10_10_10_00),
+        ("𜴎", 0b01_10_10_00),
+        ("𜴏", 0b11_10_10_00),
+        ("𜴐", 0b00_01_10_00),
+        ("𜴑", 0b10_01_10_00),
+        ("𜴒", 0b01_01_10_00),
+        ("𜴓", 0b11_01_10_00),
+        ("𜴔", 0b00_11_10_00),
+        ("𜴕", 0b10_11_10_00),
+        ("𜴖", 0b01_11_10_00),
+        ("𜴗", 0b11_11_10_00),
+        ("𜴘", 0b00_00_01_00),
+        ("𜴙", 0b10_00_01_00),
+        ("𜴚", 0b01_00_01_00),
+        ("𜴛", 0b11_00_01_00),
+        ("𜴜", 0b00_10_01_00),
+        ("𜴝", 0b10_10_01_00),
+        ("𜴞", 0b01_10_01_00),
+        ("𜴟", 0b11_10_01_00),
+        ("🯧", 0b00_01_01_00),
+        ("𜴠", 0b10_01_01_00),
+        ("𜴡", 0b01_01_01_00),
+        ("𜴢", 0b11_01_01_00),
+        ("𜴣", 0b00_11_01_00),
+        ("𜴤", 0b10_11_01_00),
+        ("𜴥", 0b01_11_01_00),
+        ("𜴦", 0b11_11_01_00),
+        ("𜴧", 0b00_00_11_00),
+        ("𜴨", 0b10_00_11_00),
+        ("𜴩", 0b01_00_11_00),
+        ("𜴪", 0b11_00_11_00),
+        ("𜴫", 0b00_10_11_00),
+        ("𜴬", 0b10_10_11_00),
+        ("𜴭", 0b01_10_11_00),
+        ("𜴮", 0b11_10_11_00),
+        ("𜴯", 0b00_01_11_00),
+        ("𜴰", 0b10_01_11_00),
+        ("𜴱", 0b01_01_11_00),
+        ("𜴲", 0b11_01_11_00),
+        ("𜴳", 0b00_11_11_00),
+        ("𜴴", 0b10_11_11_00),
+        ("𜴵", 0b01_11_11_00),
+        ("🮅", 0b11_11_11_00),
+        ("𜺣", 0b00_00_00_10),
+        ("𜴶", 0b10_00_00_10),
+        ("𜴷", 0b01_00_00_10),
+        ("𜴸", 0b11_00_00_10),
+        ("𜴹", 0b00_10_00_10),
+        ("𜴺", 0b10_10_00_10),
+        ("𜴻", 0b01_10_00_10),
+        ("𜴼", 0b11_10_00_10),
+        ("𜴽", 0b00_01_00_10),
+        ("𜴾", 0b10_01_00_10),
+        ("𜴿", 0b01_01_00_10),
+        ("𜵀", 0b11_01_00_10),
+        ("𜵁", 0b00_11_00_10),
+        ("𜵂", 0b10_11_00_10),
+        ("𜵃", 0b01_11_00_10),
+        ("𜵄", 0b11_11_00_10),
+        ("▖", 0b00_00_10_10),
+        ("𜵅", 0b10_00_10_10),
+        ("𜵆", 0b01_00_10_10),
+        ("𜵇", 0b11_00_10_10),
+        ("𜵈", 0b00_10_10_10),
+        ("▌", 0b10_10_10_10),
+        ("𜵉", 0b01_10_10_10),
+        ("𜵊", 0b11_10_10_10),
+        ("𜵋", 0b00_01_10_10),
+        ("𜵌", 0b10_01_10_10),
+        ("▞", 0b01_01_10_10),
+        ("𜵍", 0b11_01_10_10),
+        ("𜵎", 0b00_11_10_10),
+        ("𜵏", 0b10_11_10_10),
+        ("𜵐", 0b01_11_10_10),
+        ("▛", 0b11_11_10_10),
+        ("𜵑", 0b00_00_01_10),
+        ("𜵒", 0b10_00_01_10),
+        ("𜵓", 0b01_00_01_10),
+        ("𜵔", 0b11_00_01_10),
+        ("𜵕", 0b00_10_01_10),
+        ("𜵖", 0b10_10_01_10),
+        ("𜵗", 0b01_10_01_10),
+        ("𜵘", 0b11_10_01_10),
+        ("𜵙", 0b00_01_01_10),
+        ("𜵚", 0b10_01_01_10),
+        ("𜵛", 0b01_01_01_10),
+        ("𜵜", 0b11_01_01_10),
+        ("𜵝", 0b00_11_01_10),
+        ("𜵞", 0b10_11_01_10),
+        ("𜵟", 0b01_11_01_10),
+        ("𜵠", 0b11_11_01_10),
+        ("𜵡", 0b00_00_11_10),
+        ("𜵢", 0b10_00_11_10),
+        ("𜵣", 0b01_00_11_10),
+        ("𜵤", 0b11_00_11_10),
+        ("𜵥", 0b00_10_11_10),
+        ("𜵦", 0b10_10_11_10),
+        ("𜵧", 0b01_10_11_10),
+        ("𜵨", 0b11_10_11_10),
+        ("𜵩", 0b00_01_11_10),
+        ("𜵪", 0b10_01_11_10),
+        ("𜵫", 0b01_01_11_10),
+        ("𜵬", 0b11_01_11_10),
+        ("𜵭", 0b00_11_11_10),
+        ("𜵮", 0b10_11_11_10),
+        ("𜵯", 0b01_11_11_10),
+        ("𜵰", 0b11_11_11_10),
+        ("𜺠", 0b00_00_00_01),
+        ("𜵱", 0b10_00_00_01),
+        ("𜵲", 0b01_00_00_01),
+        ("𜵳", 0b11_00_00_01),
+        ("𜵴", 0b00_10_00_01),
+        ("𜵵", 0b10_10_00_01),
+        ("𜵶", 0b01_10_00_01),
+        ("𜵷", 0b11_10_00_01),
+        ("𜵸", 0b00_01_00_01),
+        ("𜵹", 0b10_01_00_01),
+        ("𜵺", 0b01_01_00_01),
+        ("𜵻", 0b11_01_00_01),
+        ("𜵼", 0b00_11_00_01),
+        ("𜵽", 0b10_11_00_01),
+        ("𜵾", 0b01_11_00_01),
+        ("𜵿", 0b11_11_00_01),
+        ("𜶀", 0b00_00_10_01),
+        ("𜶁", 0b10_00_10_01),
+        ("𜶂", 0b01_00_10_01),
+        ("𜶃", 0b11_00_10_01),
+        ("𜶄", 0b00_10_10_01),
+        ("𜶅", 0b10_10_10_01),
+        ("𜶆", 0b01_10_10_01),
+        ("𜶇", 0b11_10_10_01),
+        ("𜶈", 0b00_01_10_01),
+        ("𜶉", 0b10_01_10_01),
+        ("𜶊", 0b01_01_10_01),
+        ("𜶋", 0b11_01_10_01),
+        ("𜶌", 0b00_11_10_01),
+        ("𜶍", 0b10_11_10_01),
+        ("𜶎", 0b01_11_10_01),
+        ("𜶏", 0b11_11_10_01),
+        ("▗", 0b00_00_01_01),
+        ("𜶐", 0b10_00_01_01),
+        ("𜶑", 0b01_00_01_01),
+        ("𜶒", 0b11_00_01_01),
+        ("𜶓", 0b00_10_01_01),
+        ("▚", 0b10_10_01_01),
+        ("𜶔", 0b01_10_01_01),
+        ("𜶕", 0b11_10_01_01),
+        ("𜶖", 0b00_01_01_01),
+        ("𜶗", 0b10_01_01_01),
+        ("▐", 0b01_01_01_01),
+        ("𜶘", 0b11_01_01_01),
+        ("𜶙", 0b00_11_01_01),
+        ("𜶚", 0b10_11_01_01),
+        ("𜶛", 0b01_11_01_01),
+        ("▜", 0b11_11_01_01),
+        ("𜶜", 0b00_00_11_01),
+        ("𜶝", 0b10_00_11_01),
+        ("𜶞", 0b01_00_11_01),
+        ("𜶟", 0b11_00_11_01),
+        ("𜶠", 0b00_10_11_01),
+        ("𜶡", 0b10_10_11_01),
+        ("𜶢", 0b01_10_11_01),
+        ("𜶣", 0b11_10_11_01),
+        ("𜶤", 0b00_01_11_01),
+        ("𜶥", 0b10_01_11_01),
+        ("𜶦", 0b01_01_11_01),
+        ("𜶧", 0b11_01_11_01),
+        ("𜶨", 0b00_11_11_01),
+        ("𜶩", 0b10_11_11_01),
+        ("𜶪", 0b01_11_11_01),
+        ("𜶫", 0b11_11_11_01),
+        ("▂", 0b00_00_00_11),
+        ("𜶬", 0b10_00_00_11),
+        ("𜶭", 0b01_00_00_11),
+        ("𜶮", 0b11_00_00_11),
+        ("𜶯", 0b00_10_00_11),
+        ("𜶰", 0b10_10_00_11),
+        ("𜶱", 0b01_10_00_11),
+        ("𜶲", 0b11_10_00_11),
+        ("𜶳", 0b00_01_00_11),
+        ("𜶴", 0b10_01_00_11),
+        ("𜶵", 0b01_01_00_11),
+        ("𜶶", 0b11_01_00_11),
+        ("𜶷", 0b00_11_00_11),
+        ("𜶸", 0b10_11_00_11),
+        ("𜶹", 0b01_11_00_11),
+        ("𜶺", 0b11_11_00_11),
+        ("𜶻", 0b00_00_10_11),
+        ("𜶼", 0b10_00_10_11),
+        ("𜶽", 0b01_00_10_11),
+        ("𜶾", 0b11_00_10_11),
+        ("𜶿", 0b00_10_10_11),
+        ("𜷀", 0b10_10_10_11),
+        ("𜷁", 0b01_10_10_11),
+        ("𜷂", 0b11_10_10_11),
+        ("𜷃", 0b00_01_10_11),
+        ("𜷄", 0b10_01_10_11),
+        ("𜷅", 0b01_01_10_11),
+        ("𜷆", 0b11_01_10_11),
+        ("𜷇", 0b00_11_10_11),
+        ("𜷈", 0b10_11_10_11),
+        ("𜷉", 0b01_11_10_11),
+        ("𜷊", 0b11_11_10_11),
+        ("𜷋", 0b00_00_01_11),
+        ("𜷌", 0b10_00_01_11),
+        ("𜷍", 0b01_00_01_11),
+        ("𜷎", 0b11_00_01_11),
+        ("𜷏", 0b00_10_01_11),
+        ("𜷐", 0b10_10_01_11),
+        ("𜷑", 0b01_10_01_11),
+        ("𜷒", 0b11_10_01_11),
+        ("𜷓", 0b00_01_01_11),
+        ("𜷔", 0b10_01_01_11),
+        ("𜷕", 0b01_01_01_11),
+        ("𜷖", 0b11_01_01_11),
+        ("𜷗", 0b00_11_01_11),
+        ("𜷘", 0b10_11_01_11),
+        ("𜷙", 0b01_11_01_11),
+        ("𜷚", 0b11_11_01_11),
+        ("▄", 0b00_00_11_11),
+        ("𜷛", 0b10_00_11_11),
+        ("𜷜", 0b01_00_11_11),
+        ("𜷝", 0b11_00_11_11),
+        ("𜷞", 0b00_10_11_11),
+        ("▙", 0b10_10_11_11),
+        ("𜷟", 0b01_10_11_11),
+        ("𜷠", 0b11_10_11_11),
+        ("𜷡", 0b00_01_11_11),
+        ("𜷢", 0b10_01_11_11),
+        ("▟", 0b01_01_11_11),
+        ("𜷣", 0b11_01_11_11),
+        ("▆", 0b00_11_11_11),
+        ("𜷤", 0b10_11_11_11),
+        ("𜷥", 0b01_11_11_11),
+    ],
+};
+
+/// Box-drawing glyph sets intended for skeletonized (1-pixel-wide) strokes.
+/// The mask of each glyph indicates through which sides of the cell the
+/// strokes pass. The three variants only differ in the line weight.
+macro_rules! line_art_glyph_set {
+    (
+        $const_name:literal,
+        [
+            $h:literal, $v:literal,
+            $dr:literal, $dl:literal, $ur:literal, $ul:literal,
+            $vr:literal, $vl:literal, $hd:literal, $hu:literal, $vh:literal $(,)?
+        ]
+    ) => {
+        GlyphSetIn {
+            const_name: $const_name,
+            mask_dims: [3, 3],
+            mask_overlap: [0, 0],
+            glyph_width: 1,
+            metric: GlyphMetric::Mutation,
+            glyphs: &[
+                (" ", 0b000_000_000),
+                // An isolated pixel is most likely noise
+                (" ", 0b000_010_000),
+                ($h, 0b000_111_000),
+                ($h, 0b111_000_000),
+                ($h, 0b000_000_111),
+                ($h, 0b000_011_000),
+                ($h, 0b000_110_000),
+                ($v, 0b010_010_010),
+                ($v, 0b100_100_100),
+                ($v, 0b001_001_001),
+                ($v, 0b010_010_000),
+                ($v, 0b000_010_010),
+                ($dr, 0b000_011_010),
+                ($dr, 0b111_100_100),
+                ($dr, 0b011_010_010),
+                ($dl, 0b000_110_010),
+                ($dl, 0b111_001_001),
+                ($dl, 0b110_010_010),
+                ($ur, 0b010_011_000),
+                ($ur, 0b100_100_111),
+                ($ur, 0b010_010_011),
+                ($ul, 0b010_110_000),
+                ($ul, 0b001_001_111),
+                ($ul, 0b010_010_110),
+                ($vr, 0b010_011_010),
+                ($vr, 0b100_111_100),
+                ($vl, 0b010_110_010),
+                ($vl, 0b001_111_001),
+                ($hd, 0b000_111_010),
+                ($hd, 0b111_010_010),
+                ($hu, 0b010_111_000),
+                ($hu, 0b010_010_111),
+                ($vh, 0b010_111_010),
+                ("╱", 0b001_010_100),
+                ("╱", 0b001_010_000),
+                ("╱", 0b000_010_100),
+                ("╱", 0b010_100_000),
+                ("╱", 0b000_001_010),
+                ("╲", 0b100_010_001),
+                ("╲", 0b100_010_000),
+                ("╲", 0b000_010_001),
+                ("╲", 0b010_001_000),
+                ("╲", 0b000_100_010),
+                ("╳", 0b101_010_101),
+            ],
+        }
+    };
+}
+
+const GLYPH_SET_LINE: GlyphSetIn = line_art_glyph_set!(
+    "GLYPH_SET_LINE",
+    ["─", "│", "┌", "┐", "└", "┘", "├", "┤", "┬", "┴", "┼"]
+);
+
+const GLYPH_SET_LINE_HEAVY: GlyphSetIn = line_art_glyph_set!(
+    "GLYPH_SET_LINE_HEAVY",
+    ["━", "┃", "┏", "┓", "┗", "┛", "┣", "┫", "┳", "┻", "╋"]
+);
+
+const GLYPH_SET_LINE_DOUBLE: GlyphSetIn = line_art_glyph_set!(
+    "GLYPH_SET_LINE_DOUBLE",
+    ["═", "║", "╔", "╗", "╚", "╝", "╠", "╣", "╦", "╩", "╬"]
+);
+
+/// Structural ASCII art for environments where non-ASCII characters are
+/// unavailable (legacy terminals, source code comments, etc.)
+const GLYPH_SET_ASCII: GlyphSetIn = GlyphSetIn {
+    const_name: "GLYPH_SET_ASCII",
+    mask_dims: [3, 3],
+    mask_overlap: [0, 0],
+    glyph_width: 1,
+    metric: GlyphMetric::Mutation,
+    glyphs: &[
+        (" ", 0b000_000_000),
+        ("#", 0b111_111_111),
+        ("@", 0b111_101_111),
+        ("-", 0b000_111_000),
+        ("-", 0b000_110_000),
+        ("-", 0b000_011_000),
+        ("_", 0b000_000_111),
+        ("_", 0b000_000_110),
+        ("_", 0b000_000_011),
+        ("=", 0b111_000_111),
+        ("=", 0b000_111_111),
+        ("|", 0b010_010_010),
+        ("|", 0b100_100_100),
+        ("|", 0b001_001_001),
+        ("/", 0b001_010_100),
+        ("/", 0b011_111_110),
+        ("/", 0b001_011_110),
+        ("/", 0b011_110_100),
+        ("\\", 0b100_010_001),
+        ("\\", 0b110_111_011),
+        ("\\", 0b100_110_011),
+        ("\\", 0b110_011_001),
+        ("(", 0b010_100_010),
+        ("(", 0b011_100_011),
+        (")", 0b010_001_010),
+        (")", 0b110_001_110),
+        ("[", 0b110_100_110),
+        ("[", 0b111_100_111),
+        ("]", 0b011_001_011),
+        ("]", 0b111_001_111),
+        ("'", 0b010_000_000),
+        ("'", 0b010_010_000),
+        ("'", 0b100_000_000),
+        ("'", 0b001_000_000),
+        (".", 0b000_000_010),
+        (".", 0b000_000_100),
+        (".", 0b000_000_001),
+        (".", 0b000_010_000),
+        (",", 0b000_010_100),
+        (":", 0b010_000_010),
+        (";", 0b010_000_100),
+        // Corners
+        (".", 0b000_011_010),
+        (".", 0b000_110_010),
+        ("'", 0b010_011_000),
+        ("'", 0b010_110_000),
+        ("+", 0b111_100_100),
+        ("+", 0b111_001_001),
+        ("+", 0b100_100_111),
+        ("+", 0b001_001_111),
+        // Junctions
+        ("+", 0b010_111_010),
+        ("+", 0b010_011_010),
+        ("+", 0b010_110_010),
+        ("+", 0b000_111_010),
+        ("+", 0b010_111_000),
+        ("X", 0b101_010_101),
+        ("^", 0b010_101_000),
+        ("v", 0b000_101_010),
+        ("<", 0b001_100_001),
+        (">", 0b100_001_100),
+    ],
+};
@@ -0,0 +1,81 @@
+//! asciicast v2 recordings
+//!
+//! See <https://docs.asciinema.org/manual/asciicast/v2/> for the format.
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+/// Writes an asciicast v2 recording consisting of output events
+pub struct Writer<W> {
+    out: W,
+}
+
+impl<W: Write> Writer<W> {
+    /// Write the header. `dims` specifies the terminal size (`[columns,
+    /// lines]`).
+    pub fn new(mut out: W, [width, height]: [usize; 2]) -> io::Result<Self> {
+        writeln!(
+            out,
+            r#"{{"version": 2, "width": {}, "height": {}}}"#,
+            width, height
+        )?;
+        Ok(Self { out })
+    }
+
+    /// Write an event that prints `data` at `time` since the start of the
+    /// recording.
+    pub fn output(&mut self, time: Duration, data: &str) -> io::Result<()> {
+        write!(self.out, "[{:.6}, \"o\", ", time.as_secs_f64())?;
+        write_json_string(&mut self.out, data)?;
+        writeln!(self.out, "]")
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn write_json_string(out: &mut impl Write, s: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    // Write the characters that don't need escaping in runs
+    let mut start = 0;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '"' | '\\' | '\0'..='\x1f' | '\x7f' => {}
+            _ => continue,
+        }
+        out.write_all(&s.as_bytes()[start..i])?;
+        match ch {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            _ => write!(out, "\\u{:04x}", ch as u32)?,
+        }
+        start = i + ch.len_utf8();
+    }
+    out.write_all(&s.as_bytes()[start..])?;
+    out.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write() {
+        let mut writer = Writer::new(Vec::new(), [80, 25]).unwrap();
+        writer.output(Duration::ZERO, "⣿\r\n").unwrap();
+        writer
+            .output(Duration::from_millis(1500), "\x1b[2A\"\\\t")
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "{\"version\": 2, \"width\": 80, \"height\": 25}\n\
+             [0.000000, \"o\", \"⣿\\r\\n\"]\n\
+             [1.500000, \"o\", \"\\u001b[2A\\\"\\\\\\t\"]\n"
+        );
+    }
+}
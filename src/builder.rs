@@ -0,0 +1,375 @@
+//! Runtime glyph set construction
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::{glyphindex, Fragment, GlyphMetric, GlyphSet};
+
+/// The maximum number of pixels in a mask supported by [`GlyphSetBuilder`].
+pub const MAX_MASK_PIXELS: usize = 16;
+
+/// Constructs a [`GlyphSet`] at runtime from a list of glyphs and their masks.
+///
+/// The fragments that don't exactly match any glyph's mask are assigned the
+/// glyphs with the closest masks by the same algorithm used for the built-in
+/// glyph sets.
+///
+/// # Example
+///
+/// ```
+/// use img2text::{GlyphSet, GlyphSetBuilder};
+/// let glyph_set = GlyphSetBuilder::new([1, 2])
+///     .glyph(" ", 0b00)
+///     .glyph("▀", 0b01)
+///     .glyph("▄", 0b10)
+///     .glyph("█", 0b11)
+///     .build()
+///     .unwrap();
+/// assert_eq!(glyph_set.fragment_to_glyph(0b01), "▀");
+/// ```
+#[derive(Debug, Clone)]
+pub struct GlyphSetBuilder {
+    pub(crate) mask_dims: [usize; 2],
+    mask_overlap: [usize; 2],
+    glyph_width: usize,
+    metric: GlyphMetric,
+    glyphs: Vec<(String, Fragment)>,
+}
+
+impl GlyphSetBuilder {
+    pub fn new(mask_dims: [usize; 2]) -> Self {
+        Self {
+            mask_dims,
+            mask_overlap: [0, 0],
+            glyph_width: 1,
+            metric: GlyphMetric::Mutation,
+            glyphs: Vec::new(),
+        }
+    }
+
+    /// Construct a `GlyphSetBuilder` with the mask dimensions, mask overlap,
+    /// glyph width, and glyphs of `glyph_set` (see
+    /// [`GlyphSet::for_each_glyph`]). This is useful to rebuild a built-in
+    /// glyph set with a different [`GlyphMetric`].
+    pub fn from_glyph_set(glyph_set: &dyn GlyphSet) -> Self {
+        let mut this = Self::new(glyph_set.mask_dims());
+        this.mask_overlap(glyph_set.mask_overlap());
+        this.glyph_width(glyph_set.glyph_width());
+        glyph_set.for_each_glyph(&mut |glyph, mask| {
+            this.glyph(glyph, mask);
+        });
+        this
+    }
+
+    /// Set the number of pixels shared by adjacent cells. Defaults to
+    /// `[0, 0]`.
+    pub fn mask_overlap(&mut self, mask_overlap: [usize; 2]) -> &mut Self {
+        self.mask_overlap = mask_overlap;
+        self
+    }
+
+    /// Set the number of display columns occupied by each glyph (e.g., `2`
+    /// for `"██"` or full-width characters). Defaults to `1`.
+    pub fn glyph_width(&mut self, glyph_width: usize) -> &mut Self {
+        self.glyph_width = glyph_width;
+        self
+    }
+
+    /// Set the method to choose glyphs for the fragments that don't exactly
+    /// match any glyph's mask. Defaults to [`GlyphMetric::Mutation`].
+    pub fn metric(&mut self, metric: GlyphMetric) -> &mut Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Add a glyph. `mask` is a bitmap of dimensions `mask_dims` in the
+    /// standard order (LSB = upper left corner, MSB = lower right corner).
+    ///
+    /// If two glyphs have identical masks, the first one takes precedence.
+    pub fn glyph(&mut self, glyph: impl Into<String>, mask: Fragment) -> &mut Self {
+        self.glyphs.push((glyph.into(), mask));
+        self
+    }
+
+    /// Iterate over the glyphs added so far and their masks.
+    pub fn glyphs(&self) -> impl Iterator<Item = (&str, Fragment)> + '_ {
+        self.glyphs
+            .iter()
+            .map(|(glyph, mask)| (glyph.as_str(), *mask))
+    }
+
+    pub fn build(&self) -> Result<OwnedGlyphSet, BuildGlyphSetError> {
+        let mask_dims = self.mask_dims;
+        let mask_overlap = self.mask_overlap;
+        let num_pixels = mask_dims[0] * mask_dims[1];
+
+        if mask_dims[0] == 0 || mask_dims[1] == 0 || num_pixels > MAX_MASK_PIXELS {
+            return Err(BuildGlyphSetError::BadMaskDims);
+        }
+        if mask_overlap[0] >= mask_dims[0] || mask_overlap[1] >= mask_dims[1] {
+            return Err(BuildGlyphSetError::BadMaskOverlap);
+        }
+        if self.glyph_width == 0 {
+            return Err(BuildGlyphSetError::BadGlyphWidth);
+        }
+        if self.glyphs.is_empty() {
+            return Err(BuildGlyphSetError::NoGlyphs);
+        }
+        if let Some(glyph_i) = self
+            .glyphs
+            .iter()
+            .position(|&(_, mask)| mask >> num_pixels != 0)
+        {
+            return Err(BuildGlyphSetError::MaskOutOfRange { glyph_i });
+        }
+
+        let masks: Vec<Fragment> = self.glyphs.iter().map(|&(_, mask)| mask).collect();
+        let index = glyphindex::fill_index(mask_dims, &masks, self.metric);
+
+        Ok(OwnedGlyphSet {
+            mask_dims,
+            mask_overlap,
+            max_glyph_len: self.glyphs.iter().map(|x| x.0.len()).max().unwrap(),
+            glyph_width: self.glyph_width,
+            glyphs: self.glyphs.clone(),
+            index,
+        })
+    }
+}
+
+/// An error returned by [`GlyphSetBuilder::build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildGlyphSetError {
+    /// The mask dimensions are zero or too large.
+    BadMaskDims,
+    /// The mask overlap is not smaller than the mask dimensions.
+    BadMaskOverlap,
+    /// The glyph width is zero.
+    BadGlyphWidth,
+    /// No glyphs were specified.
+    NoGlyphs,
+    /// The mask of the `glyph_i`-th glyph has bits outside the mask
+    /// dimensions.
+    MaskOutOfRange { glyph_i: usize },
+}
+
+impl fmt::Display for BuildGlyphSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMaskDims => write!(
+                f,
+                "mask dimensions must be non-zero and contain at most {} pixels",
+                MAX_MASK_PIXELS
+            ),
+            Self::BadMaskOverlap => write!(f, "mask overlap must be smaller than mask dimensions"),
+            Self::BadGlyphWidth => write!(f, "glyph width must be non-zero"),
+            Self::NoGlyphs => write!(f, "glyph set must contain at least one glyph"),
+            Self::MaskOutOfRange { glyph_i } => write!(
+                f,
+                "mask of glyph #{} has bits outside the mask dimensions",
+                glyph_i
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildGlyphSetError {}
+
+/// A [`GlyphSet`] created by [`GlyphSetBuilder`].
+#[derive(Debug, Clone)]
+pub struct OwnedGlyphSet {
+    mask_dims: [usize; 2],
+    mask_overlap: [usize; 2],
+    max_glyph_len: usize,
+    glyph_width: usize,
+    glyphs: Vec<(String, Fragment)>,
+    /// The glyph index for each fragment
+    index: Vec<usize>,
+}
+
+impl GlyphSet for OwnedGlyphSet {
+    fn mask_dims(&self) -> [usize; 2] {
+        self.mask_dims
+    }
+
+    fn mask_overlap(&self) -> [usize; 2] {
+        self.mask_overlap
+    }
+
+    fn fragment_to_glyph(&self, fragment: u64) -> &str {
+        &self.glyphs[self.index[fragment as usize]].0
+    }
+
+    fn fragment_to_glyph_index(&self, fragment: Fragment) -> Option<usize> {
+        Some(self.index[fragment as usize])
+    }
+
+    fn max_glyph_len(&self) -> usize {
+        self.max_glyph_len
+    }
+
+    fn glyph_width(&self) -> usize {
+        self.glyph_width
+    }
+
+    fn glyph_to_mask(&self, glyph: &str) -> Option<Fragment> {
+        self.glyphs
+            .iter()
+            .find(|(g, _)| g == glyph)
+            .map(|&(_, mask)| mask)
+    }
+
+    fn for_each_glyph(&self, f: &mut dyn FnMut(&str, Fragment)) {
+        for (glyph, mask) in self.glyphs.iter() {
+            f(glyph, *mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GLYPH_SET_1X1_WIDE, GLYPH_SET_2X2, GLYPH_SET_SLC};
+
+    #[test]
+    fn exact_matches() {
+        let mut builder = GlyphSetBuilder::new([2, 2]);
+        for fragment in 0..16 {
+            builder.glyph(GLYPH_SET_2X2.fragment_to_glyph(fragment), fragment);
+        }
+        let gs = builder.build().unwrap();
+        assert_eq!(gs.max_glyph_len(), GLYPH_SET_2X2.max_glyph_len());
+        for fragment in 0..16 {
+            assert_eq!(
+                gs.fragment_to_glyph(fragment),
+                GLYPH_SET_2X2.fragment_to_glyph(fragment)
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_matches() {
+        let gs = GlyphSetBuilder::new([2, 2])
+            .glyph(" ", 0b00_00)
+            .glyph("▌", 0b01_01)
+            .build()
+            .unwrap();
+        assert_eq!(gs.fragment_to_glyph(0b00_01), "▌");
+        assert_eq!(gs.fragment_to_glyph(0b10_00), " ");
+
+        // No glyph has a superset mask of `0b11_11`
+        let gs = GlyphSetBuilder::new([2, 2])
+            .glyph("a", 0b00_01)
+            .glyph("b", 0b01_11)
+            .build()
+            .unwrap();
+        assert_eq!(gs.fragment_to_glyph(0b11_11), "b");
+    }
+
+    #[test]
+    fn from_glyph_set() {
+        let gs = GlyphSetBuilder::from_glyph_set(GLYPH_SET_SLC)
+            .build()
+            .unwrap();
+        for fragment in 0..512 {
+            assert_eq!(
+                gs.fragment_to_glyph(fragment),
+                GLYPH_SET_SLC.fragment_to_glyph(fragment)
+            );
+        }
+
+        let gs = GlyphSetBuilder::from_glyph_set(GLYPH_SET_1X1_WIDE)
+            .build()
+            .unwrap();
+        assert_eq!(gs.glyph_width(), 2);
+    }
+
+    #[test]
+    fn metrics() {
+        // (Masks are in the standard order)
+        let build = |metric, glyphs: &[(&str, Fragment)]| {
+            let mut builder = GlyphSetBuilder::new([3, 3]);
+            builder.metric(metric);
+            for &(glyph, mask) in glyphs {
+                builder.glyph(glyph, mask);
+            }
+            builder.build().unwrap()
+        };
+
+        // The top-left and center pixels. Both glyphs are one pixel off, but
+        // the center pixel is more important.
+        let glyphs = [("corner", 0b000_000_001), ("center", 0b000_010_000)];
+        let fragment = 0b000_010_001;
+        let gs = build(GlyphMetric::Hamming, &glyphs);
+        assert_eq!(gs.fragment_to_glyph(fragment), "corner");
+        let gs = build(GlyphMetric::PositionWeighted, &glyphs);
+        assert_eq!(gs.fragment_to_glyph(fragment), "center");
+
+        // A vertical stroke on the left edge. It's closer to nothing in
+        // Hamming distance but looks closer to a center stroke.
+        let glyphs = [(" ", 0), ("|", 0b010_010_010)];
+        let fragment = 0b001_001_001;
+        let gs = build(GlyphMetric::Hamming, &glyphs);
+        assert_eq!(gs.fragment_to_glyph(fragment), " ");
+        let gs = build(GlyphMetric::BlurL2, &glyphs);
+        assert_eq!(gs.fragment_to_glyph(fragment), "|");
+
+        // Exact matches always win
+        for &metric in &[
+            GlyphMetric::Hamming,
+            GlyphMetric::PositionWeighted,
+            GlyphMetric::BlurL2,
+        ] {
+            let gs = build(metric, &glyphs);
+            assert_eq!(gs.fragment_to_glyph(0b010_010_010), "|");
+            assert_eq!(gs.fragment_to_glyph(0), " ");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            GlyphSetBuilder::new([0, 2])
+                .glyph(" ", 0)
+                .build()
+                .unwrap_err(),
+            BuildGlyphSetError::BadMaskDims
+        );
+        assert_eq!(
+            GlyphSetBuilder::new([5, 4])
+                .glyph(" ", 0)
+                .build()
+                .unwrap_err(),
+            BuildGlyphSetError::BadMaskDims
+        );
+        assert_eq!(
+            GlyphSetBuilder::new([2, 2])
+                .mask_overlap([2, 0])
+                .glyph(" ", 0)
+                .build()
+                .unwrap_err(),
+            BuildGlyphSetError::BadMaskOverlap
+        );
+        assert_eq!(
+            GlyphSetBuilder::new([2, 2])
+                .glyph_width(0)
+                .glyph(" ", 0)
+                .build()
+                .unwrap_err(),
+            BuildGlyphSetError::BadGlyphWidth
+        );
+        assert_eq!(
+            GlyphSetBuilder::new([2, 2]).build().unwrap_err(),
+            BuildGlyphSetError::NoGlyphs
+        );
+        assert_eq!(
+            GlyphSetBuilder::new([2, 2])
+                .glyph(" ", 0)
+                .glyph("x", 0b1_00_00)
+                .build()
+                .unwrap_err(),
+            BuildGlyphSetError::MaskOutOfRange { glyph_i: 1 }
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! ANSI color output
+use core::fmt;
+
+use crate::{Fragment, Rgb};
+
+/// The color palette used for SGR escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// The basic 16 colors (`ESC [ 30–37, 90–97 m`)
+    Ansi16,
+    /// The xterm 256-color palette (`ESC [ 38 ; 5 ; n m`)
+    Ansi256,
+    /// 24-bit colors (`ESC [ 38 ; 2 ; r ; g ; b m`)
+    Truecolor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ColorOpts {
+    pub mode: ColorMode,
+    /// Set the background color of each cell in addition to the foreground
+    /// color.
+    pub background: bool,
+}
+
+impl ColorOpts {
+    pub fn new(mode: ColorMode) -> Self {
+        Self {
+            mode,
+            background: false,
+        }
+    }
+
+    /// The maximum number of bytes of escape sequences preceding each glyph.
+    pub(crate) fn max_escape_len_per_glyph(&self) -> usize {
+        // "\x1b[38;2;255;255;255;48;2;255;255;255m"
+        let [fg_param_len, bg_param_len] = match self.mode {
+            ColorMode::Ansi16 => ["97".len(), "107".len()],
+            ColorMode::Ansi256 => ["38;5;255".len(), "48;5;255".len()],
+            ColorMode::Truecolor => ["38;2;255;255;255".len(), "48;2;255;255;255".len()],
+        };
+        let mut len = "\x1b[m".len() + fg_param_len;
+        if self.background {
+            len += ";".len() + bg_param_len;
+        }
+        len
+    }
+
+    /// The maximum number of bytes of escape sequences at the end of each line.
+    pub(crate) fn max_escape_len_per_line(&self) -> usize {
+        SGR_RESET.len()
+    }
+}
+
+const SGR_RESET: &str = "\x1b[0m";
+
+/// The xterm default values of the basic 16 colors
+const ANSI16_PALETTE: [Rgb; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// The channel values of the xterm 6x6x6 color cube
+const ANSI256_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// A color representable by a particular [`ColorMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnsiColor {
+    Indexed16(u8),
+    Indexed256(u8),
+    Rgb(Rgb),
+}
+
+fn distance_sq(x: Rgb, y: Rgb) -> u32 {
+    x.iter()
+        .zip(y.iter())
+        .map(|(&a, &b)| {
+            let d = a as i32 - b as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
+
+impl AnsiColor {
+    pub(crate) fn quantize(rgb: Rgb, mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Truecolor => Self::Rgb(rgb),
+            ColorMode::Ansi16 => {
+                let (i, _) = ANSI16_PALETTE
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|&(_, &c)| distance_sq(c, rgb))
+                    .unwrap();
+                Self::Indexed16(i as u8)
+            }
+            ColorMode::Ansi256 => {
+                // The nearest color in the 6x6x6 color cube (16–231)
+                let cube_i = rgb.map(|x| {
+                    (0..6)
+                        .min_by_key(|&i| (ANSI256_CUBE_LEVELS[i] as i32 - x as i32).abs())
+                        .unwrap()
+                });
+                let cube_rgb = cube_i.map(|i| ANSI256_CUBE_LEVELS[i]);
+
+                // The nearest color in the grayscale ramp (232–255)
+                let mean = rgb.iter().map(|&x| x as u32).sum::<u32>() / 3;
+                let gray_i = (mean.saturating_sub(3) / 10).min(23);
+                let gray = (8 + gray_i * 10) as u8;
+
+                if distance_sq([gray; 3], rgb) < distance_sq(cube_rgb, rgb) {
+                    Self::Indexed256(232 + gray_i as u8)
+                } else {
+                    Self::Indexed256((16 + cube_i[0] * 36 + cube_i[1] * 6 + cube_i[2]) as u8)
+                }
+            }
+        }
+    }
+
+    fn write_sgr_param(&self, out: &mut impl fmt::Write, background: bool) -> fmt::Result {
+        match *self {
+            Self::Indexed16(i) => {
+                let base = match (background, i < 8) {
+                    (false, true) => 30,
+                    (false, false) => 90 - 8,
+                    (true, true) => 40,
+                    (true, false) => 100 - 8,
+                };
+                write!(out, "{}", base + i as u32)
+            }
+            Self::Indexed256(i) => write!(out, "{};5;{}", if background { 48 } else { 38 }, i),
+            Self::Rgb([r, g, b]) => write!(
+                out,
+                "{};2;{};{};{}",
+                if background { 48 } else { 38 },
+                r,
+                g,
+                b
+            ),
+        }
+    }
+}
+
+/// Tracks the current SGR state of the output and emits escape sequences only
+/// when the colors change.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SgrWriter {
+    fg: Option<AnsiColor>,
+    bg: Option<AnsiColor>,
+}
+
+impl SgrWriter {
+    /// Update the current colors. `None` leaves the corresponding color
+    /// unchanged.
+    pub(crate) fn set(
+        &mut self,
+        fg: Option<Rgb>,
+        bg: Option<Rgb>,
+        mode: ColorMode,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        let fg = fg
+            .map(|c| AnsiColor::quantize(c, mode))
+            .filter(|&c| Some(c) != self.fg);
+        let bg = bg
+            .map(|c| AnsiColor::quantize(c, mode))
+            .filter(|&c| Some(c) != self.bg);
+
+        if fg.is_none() && bg.is_none() {
+            return Ok(());
+        }
+
+        out.write_str("\x1b[")?;
+        if let Some(fg) = fg {
+            fg.write_sgr_param(out, false)?;
+            self.fg = Some(fg);
+        }
+        if let Some(bg) = bg {
+            if fg.is_some() {
+                out.write_str(";")?;
+            }
+            bg.write_sgr_param(out, true)?;
+            self.bg = Some(bg);
+        }
+        out.write_str("m")
+    }
+
+    /// Restore the default colors if they have been changed.
+    pub(crate) fn reset(&mut self, out: &mut impl fmt::Write) -> fmt::Result {
+        if self.fg.is_some() || self.bg.is_some() {
+            *self = Self::default();
+            out.write_str(SGR_RESET)?;
+        }
+        Ok(())
+    }
+}
+
+/// Calculate the average colors of the set and unset pixels of `fragment`,
+/// respectively.
+///
+/// `rgb_rows` is a row group of stride `stride` that covers the cell. The cell
+/// begins at `x0` and has dimensions `mask_dims` (possibly clipped by
+/// `stride`).
+pub(crate) fn average_cell_colors(
+    rgb_rows: &[Rgb],
+    stride: usize,
+    x0: usize,
+    mask_dims: [usize; 2],
+    fragment: Fragment,
+) -> [Option<Rgb>; 2] {
+    let mut sums = [[0u32; 3]; 2];
+    let mut counts = [0u32; 2];
+
+    for y in 0..mask_dims[1] {
+        let row = &rgb_rows[y * stride..][..stride];
+        for x in 0..mask_dims[0] {
+            let rgb = if let Some(rgb) = row.get(x0 + x) {
+                rgb
+            } else {
+                continue;
+            };
+            let on = (fragment >> (x + y * mask_dims[0])) & 1 != 0;
+            let k = (!on) as usize;
+            for (sum, &c) in sums[k].iter_mut().zip(rgb.iter()) {
+                *sum += c as u32;
+            }
+            counts[k] += 1;
+        }
+    }
+
+    let mut out = [None; 2];
+    for ((out, sum), &count) in out.iter_mut().zip(sums.iter()).zip(counts.iter()) {
+        if count > 0 {
+            *out = Some(sum.map(|x| ((x + count / 2) / count) as u8));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantize() {
+        let red = [255, 0, 0];
+        assert_eq!(
+            AnsiColor::quantize(red, ColorMode::Ansi16),
+            AnsiColor::Indexed16(9)
+        );
+        assert_eq!(
+            AnsiColor::quantize(red, ColorMode::Ansi256),
+            AnsiColor::Indexed256(196)
+        );
+        assert_eq!(
+            AnsiColor::quantize([128, 128, 128], ColorMode::Ansi256),
+            AnsiColor::Indexed256(244)
+        );
+        assert_eq!(
+            AnsiColor::quantize(red, ColorMode::Truecolor),
+            AnsiColor::Rgb(red)
+        );
+    }
+
+    #[test]
+    fn sgr_writer() {
+        let mut out = String::new();
+        let mut sgr = SgrWriter::default();
+        sgr.set(Some([255, 0, 0]), None, ColorMode::Truecolor, &mut out)
+            .unwrap();
+        sgr.set(Some([255, 0, 0]), None, ColorMode::Truecolor, &mut out)
+            .unwrap();
+        sgr.set(
+            Some([255, 0, 0]),
+            Some([0, 0, 0]),
+            ColorMode::Ansi256,
+            &mut out,
+        )
+        .unwrap();
+        sgr.reset(&mut out).unwrap();
+        sgr.reset(&mut out).unwrap();
+        assert_eq!(out, "\x1b[38;2;255;0;0m\x1b[38;5;196;48;5;16m\x1b[0m");
+    }
+
+    #[test]
+    fn max_escape_len_per_glyph() {
+        let levels = || (0..=17).map(|i| i * 15);
+        for &mode in &[ColorMode::Ansi16, ColorMode::Ansi256, ColorMode::Truecolor] {
+            for &background in &[false, true] {
+                let mut color_opts = ColorOpts::new(mode);
+                color_opts.background = background;
+
+                let mut max_len = 0;
+                for r in levels() {
+                    for g in levels() {
+                        for b in levels() {
+                            let mut out = String::new();
+                            let rgb = Some([r, g, b]);
+                            SgrWriter::default()
+                                .set(rgb, rgb.filter(|_| background), mode, &mut out)
+                                .unwrap();
+                            max_len = max_len.max(out.len());
+                        }
+                    }
+                }
+
+                assert_eq!(
+                    max_len,
+                    color_opts.max_escape_len_per_glyph(),
+                    "{:?}",
+                    color_opts
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Glyph-level error diffusion
+use alloc::vec::Vec;
+
+use crate::{fidelity::closest_glyph_mask, Fragment, GlyphSet};
+
+/// The fixed-point representation of an "on" pixel
+const ONE: i32 = 256;
+
+/// Diffuses the mismatch between each cell's input fragment and its glyph's
+/// mask into the cells not processed yet, like Floyd–Steinberg dithering does
+/// for pixels. Each pixel's error is propagated to the same position in the
+/// neighboring cells.
+#[derive(Default, Debug)]
+pub(crate) struct ErrorDiffuser {
+    /// The accumulated errors of the current row of cells. Each cell has one
+    /// value per pixel. A padding cell is placed at each end.
+    cur: Vec<i32>,
+    /// The accumulated errors of the next row of cells
+    next: Vec<i32>,
+}
+
+impl ErrorDiffuser {
+    /// Forget the accumulated errors. Must be called before processing a new
+    /// image.
+    pub fn reset(&mut self) {
+        self.cur.clear();
+        self.next.clear();
+    }
+
+    /// Process a row of cells from left to right, replacing each element of
+    /// `fragments` with the error-adjusted fragment to choose the glyph from.
+    pub fn diffuse_line(&mut self, glyph_set: &dyn GlyphSet, fragments: &mut [Fragment]) {
+        let mask_dims = glyph_set.mask_dims();
+        let num_pixels = mask_dims[0] * mask_dims[1];
+        let len = (fragments.len() + 2) * num_pixels;
+        if self.cur.len() != len {
+            self.cur.clear();
+            self.cur.resize(len, 0);
+            self.next.clear();
+            self.next.resize(len, 0);
+        }
+
+        let mut values = [0i32; 64];
+        let values = &mut values[..num_pixels];
+
+        for (out_x, fragment) in fragments.iter_mut().enumerate() {
+            let cell = (out_x + 1) * num_pixels;
+
+            // Apply the accumulated error. Clamping keeps the error from
+            // building up where no glyph can compensate it.
+            let mut adjusted: Fragment = 0;
+            for (i, value) in values.iter_mut().enumerate() {
+                let on = (*fragment >> i & 1) as i32;
+                *value = (on * ONE + self.cur[cell + i]).clamp(-ONE, ONE * 2);
+                adjusted |= ((*value >= ONE / 2) as Fragment) << i;
+            }
+
+            let glyph = glyph_set.fragment_to_glyph(adjusted);
+            let mask = closest_glyph_mask(glyph_set, glyph, adjusted).unwrap_or(adjusted);
+
+            // Distribute the error of each pixel
+            for (i, &value) in values.iter().enumerate() {
+                let error = value - (mask >> i & 1) as i32 * ONE;
+                let [right, below_left, below] = [error * 7 / 16, error * 3 / 16, error * 5 / 16];
+                self.cur[cell + num_pixels + i] += right;
+                self.next[cell - num_pixels + i] += below_left;
+                self.next[cell + i] += below;
+                self.next[cell + num_pixels + i] += error - right - below_left - below;
+            }
+
+            *fragment = adjusted;
+        }
+
+        core::mem::swap(&mut self.cur, &mut self.next);
+        for x in self.next.iter_mut() {
+            *x = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testutil::NoiseImage, text2bmp, Bmp2text, Bmp2textOpts, ImageRead, GLYPH_SET_SLC};
+
+    /// Convert `image` with `GLYPH_SET_SLC` and get the ratio of pixels set in
+    /// the glyph masks.
+    fn output_density(image: &impl ImageRead, error_diffusion: bool) -> f64 {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_SLC;
+        opts.error_diffusion = error_diffusion;
+        let mut text = String::new();
+        Bmp2text::new()
+            .transform_and_write(image, &opts, &mut text)
+            .unwrap();
+        let bitmap = text2bmp(&text, GLYPH_SET_SLC).unwrap();
+        let num_set = bitmap.pixels().iter().filter(|&&on| on).count();
+        num_set as f64 / bitmap.pixels().len() as f64
+    }
+
+    #[test]
+    fn preserves_density() {
+        for &period in &[2, 3, 4, 5, 7, 10] {
+            let image = NoiseImage([120, 120], period);
+            let mut num_set = 0;
+            let mut row = [0; 8];
+            for y in 0..120 {
+                image.copy_line_as_spans_to(y, &mut row);
+                num_set += row.iter().map(|x| x.count_ones()).sum::<u32>();
+            }
+            let expected = num_set as f64 / (120.0 * 120.0);
+
+            let plain_error = (output_density(&image, false) - expected).abs();
+            let diffused_error = (output_density(&image, true) - expected).abs();
+            assert!(
+                diffused_error < 0.06 && diffused_error < plain_error,
+                "period = {}, expected = {}, plain_error = {}, diffused_error = {}",
+                period,
+                expected,
+                plain_error,
+                diffused_error
+            );
+        }
+    }
+}
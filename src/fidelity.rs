@@ -0,0 +1,158 @@
+//! Reconstruction error measurement
+use crate::{Fragment, GlyphSet};
+
+/// The reconstruction error of a cell: the Hamming distance between the input
+/// fragment and the closest mask of the chosen glyph. `None` if the glyph
+/// doesn't represent a specific pattern (see [`GlyphSet::glyph_to_mask`]).
+pub type CellError = Option<u32>;
+
+/// Calculate the reconstruction error of a cell whose input fragment is
+/// `fragment`.
+pub fn cell_error(glyph_set: &dyn GlyphSet, fragment: Fragment) -> CellError {
+    glyph_error(glyph_set, glyph_set.fragment_to_glyph(fragment), fragment)
+}
+
+/// Calculate the reconstruction error of a cell whose input fragment is
+/// `fragment` and glyph is `glyph`.
+pub fn glyph_error(glyph_set: &dyn GlyphSet, glyph: &str, fragment: Fragment) -> CellError {
+    closest_glyph_mask(glyph_set, glyph, fragment).map(|mask| (mask ^ fragment).count_ones())
+}
+
+/// Get the mask of `glyph` closest to `fragment` in Hamming distance. Some
+/// glyph sets (e.g., `slc`) map multiple masks to the same glyph, in which
+/// case [`GlyphSet::glyph_to_mask`] only returns one of them.
+pub(crate) fn closest_glyph_mask(
+    glyph_set: &dyn GlyphSet,
+    glyph: &str,
+    fragment: Fragment,
+) -> Option<Fragment> {
+    let first = glyph_set.glyph_to_mask(glyph);
+    if first == Some(fragment) {
+        return first;
+    }
+
+    let mut closest: Option<Fragment> = None;
+    glyph_set.for_each_glyph(&mut |g, mask| {
+        if g == glyph
+            && closest.map_or(true, |c| {
+                (mask ^ fragment).count_ones() < (c ^ fragment).count_ones()
+            })
+        {
+            closest = Some(mask);
+        }
+    });
+    // Glyph sets not defined by a list of glyphs
+    closest.or(first)
+}
+
+/// Aggregate reconstruction error statistics, collected by
+/// [`crate::Bmp2text::transform_lines_with_errors`].
+///
+/// The pixels shared by overlapping cells are counted once per cell.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorStats {
+    /// The number of cells
+    pub num_cells: usize,
+    /// The number of cells excluded from the measurement because their glyphs
+    /// don't represent specific patterns. The fields below don't include
+    /// them.
+    pub num_unmeasured_cells: usize,
+    /// The number of cells reproduced exactly
+    pub num_exact_cells: usize,
+    /// The number of pixels in the measured cells
+    pub num_pixels: usize,
+    /// The number of mismatching pixels in the measured cells
+    pub num_error_pixels: usize,
+    /// The largest reconstruction error of a cell
+    pub max_cell_error: u32,
+}
+
+impl ErrorStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a cell of `num_pixels` pixels.
+    pub fn add_cell(&mut self, error: CellError, num_pixels: usize) {
+        self.num_cells += 1;
+        if let Some(error) = error {
+            self.num_exact_cells += (error == 0) as usize;
+            self.num_pixels += num_pixels;
+            self.num_error_pixels += error as usize;
+            self.max_cell_error = self.max_cell_error.max(error);
+        } else {
+            self.num_unmeasured_cells += 1;
+        }
+    }
+
+    /// Get the ratio of correctly reproduced pixels (`1.0` = perfect). Returns
+    /// `None` if no cells were measured.
+    pub fn fidelity(&self) -> Option<f64> {
+        if self.num_pixels == 0 {
+            None
+        } else {
+            Some(1.0 - self.num_error_pixels as f64 / self.num_pixels as f64)
+        }
+    }
+
+    /// Get the average reconstruction error per measured cell. Returns `None`
+    /// if no cells were measured.
+    pub fn mean_cell_error(&self) -> Option<f64> {
+        let num_measured_cells = self.num_cells - self.num_unmeasured_cells;
+        if num_measured_cells == 0 {
+            None
+        } else {
+            Some(self.num_error_pixels as f64 / num_measured_cells as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GLYPH_SET_1X2, GLYPH_SET_2X2, GLYPH_SET_ASCII, GLYPH_SET_RAMP, GLYPH_SET_SLC};
+
+    #[test]
+    fn cell_error_exact() {
+        for fragment in 0..4 {
+            assert_eq!(cell_error(GLYPH_SET_1X2, fragment), Some(0));
+        }
+        for fragment in 0..16 {
+            assert_eq!(cell_error(GLYPH_SET_2X2, fragment), Some(0));
+        }
+        assert_eq!(cell_error(GLYPH_SET_RAMP, 0), None);
+    }
+
+    #[test]
+    fn cell_error_exact_multi_mask() {
+        // These glyph sets map multiple masks to some glyphs
+        for &glyph_set in &[GLYPH_SET_SLC, GLYPH_SET_ASCII] {
+            glyph_set.for_each_glyph(&mut |glyph, mask| {
+                assert_eq!(cell_error(glyph_set, mask), Some(0), "{:?}", glyph);
+            });
+        }
+    }
+
+    #[test]
+    fn cell_error_approx() {
+        // A checkerboard pattern doesn't have an exact glyph in `slc`
+        let error = cell_error(GLYPH_SET_SLC, 0b101_010_101).unwrap();
+        assert!(error > 0);
+    }
+
+    #[test]
+    fn stats() {
+        let mut stats = ErrorStats::new();
+        assert_eq!(stats.fidelity(), None);
+        stats.add_cell(Some(0), 4);
+        stats.add_cell(Some(2), 4);
+        stats.add_cell(None, 4);
+        assert_eq!(stats.num_cells, 3);
+        assert_eq!(stats.num_unmeasured_cells, 1);
+        assert_eq!(stats.num_exact_cells, 1);
+        assert_eq!(stats.max_cell_error, 2);
+        assert_eq!(stats.fidelity(), Some(0.75));
+        assert_eq!(stats.mean_cell_error(), Some(1.0));
+    }
+}
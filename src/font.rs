@@ -0,0 +1,393 @@
+//! Glyph set construction from fonts
+//!
+//! The methods in this module render each character with a font, downsample
+//! the result to the mask dimensions, and add the character to
+//! [`GlyphSetBuilder`] with the resulting mask.
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use crate::{GlyphSetBuilder, MAX_MASK_PIXELS};
+
+/// An error returned by [`GlyphSetBuilder::add_bdf_glyphs`] or
+/// [`GlyphSetBuilder::add_ttf_glyphs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FontError {
+    /// The font data is malformed.
+    InvalidFont,
+    /// The BDF font is malformed at the specified line (1-based).
+    InvalidBdf { line: usize },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFont => write!(f, "malformed font data"),
+            Self::InvalidBdf { line } => write!(f, "malformed BDF font at line {}", line),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FontError {}
+
+impl GlyphSetBuilder {
+    /// Add glyphs by rendering `chars` with a BDF bitmap font. The cell of
+    /// each glyph is the font's bounding box (`FONTBOUNDINGBOX`).
+    ///
+    /// The characters not included in the font are ignored. `ENCODING` is
+    /// assumed to represent Unicode code points.
+    pub fn add_bdf_glyphs(
+        &mut self,
+        bdf: &str,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<&mut Self, FontError> {
+        let font = BdfFont::parse(bdf)?;
+        let [cell_w, cell_h] = [font.bbx[0] as usize, font.bbx[1] as usize];
+
+        if !self.has_valid_mask_dims() {
+            // Leave it to `build` to report the error
+            return Ok(self);
+        }
+
+        let mut coverage = vec![0.0; cell_w * cell_h];
+        let mut rendered = Vec::new();
+
+        for ch in chars {
+            let glyph = if let Some(x) = font.glyphs.iter().find(|g| g.encoding == ch as i64) {
+                x
+            } else {
+                continue;
+            };
+
+            for x in coverage.iter_mut() {
+                *x = 0.0;
+            }
+
+            // Place the glyph's bounding box relative to the font's bounding
+            // box
+            let [w, h, x_off, y_off] = glyph.bbx;
+            let left = x_off - font.bbx[2];
+            let top = (font.bbx[1] + font.bbx[3]) - (h + y_off);
+            for (gy, row) in glyph
+                .bitmap
+                .chunks_exact(glyph.row_len.max(1))
+                .enumerate()
+                .take(h as usize)
+            {
+                for gx in 0..w {
+                    let bit = (row[gx as usize / 8] >> (7 - gx % 8)) & 1 != 0;
+                    let (x, y) = (left + gx, top + gy as i64);
+                    if bit && (0..cell_w as i64).contains(&x) && (0..cell_h as i64).contains(&y) {
+                        coverage[x as usize + y as usize * cell_w] = 1.0;
+                    }
+                }
+            }
+
+            rendered.push((
+                ch,
+                region_coverage(&coverage, [cell_w, cell_h], self.mask_dims),
+            ));
+        }
+
+        self.add_rendered_glyphs(rendered);
+        Ok(self)
+    }
+
+    /// Add glyphs by rendering `chars` with a TrueType or OpenType font. The
+    /// cell of each glyph is as wide as the widest character and as tall as the
+    /// font's ascent plus descent.
+    ///
+    /// The characters not included in the font are ignored.
+    #[cfg(all(feature = "ab_glyph", feature = "std"))]
+    pub fn add_ttf_glyphs(
+        &mut self,
+        font_data: &[u8],
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<&mut Self, FontError> {
+        use ab_glyph::{Font, FontRef, ScaleFont};
+
+        /// The number of rendered pixels per mask pixel (vertically)
+        const OVERSAMPLING: usize = 8;
+
+        let font = FontRef::try_from_slice(font_data).map_err(|_| FontError::InvalidFont)?;
+
+        if !self.has_valid_mask_dims() {
+            // Leave it to `build` to report the error
+            return Ok(self);
+        }
+
+        let font = font.as_scaled((self.mask_dims[1] * OVERSAMPLING) as f32);
+
+        let glyph_ids: Vec<(char, ab_glyph::GlyphId)> = chars
+            .into_iter()
+            .map(|ch| (ch, font.glyph_id(ch)))
+            .filter(|&(_, id)| id.0 != 0) // `.notdef`
+            .collect();
+
+        let cell_w = glyph_ids
+            .iter()
+            .map(|&(_, id)| font.h_advance(id))
+            .fold(0.0f32, f32::max)
+            .ceil() as usize;
+        let cell_h = font.height().ceil() as usize;
+
+        let mut coverage = vec![0.0; cell_w * cell_h];
+        let mut rendered = Vec::new();
+
+        for (ch, id) in glyph_ids {
+            for x in coverage.iter_mut() {
+                *x = 0.0;
+            }
+
+            let glyph =
+                id.with_scale_and_position(font.scale(), ab_glyph::point(0.0, font.ascent()));
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, c| {
+                    let x = bounds.min.x as i64 + gx as i64;
+                    let y = bounds.min.y as i64 + gy as i64;
+                    if (0..cell_w as i64).contains(&x) && (0..cell_h as i64).contains(&y) {
+                        coverage[x as usize + y as usize * cell_w] = c;
+                    }
+                });
+            }
+
+            rendered.push((
+                ch,
+                region_coverage(&coverage, [cell_w, cell_h], self.mask_dims),
+            ));
+        }
+
+        self.add_rendered_glyphs(rendered);
+        Ok(self)
+    }
+
+    /// Add glyphs given the per-mask-pixel coverage of each character. A mask
+    /// pixel is set if its coverage is at least a quarter of the highest
+    /// coverage among all mask pixels of all characters so that thin strokes
+    /// are not lost to downsampling.
+    fn add_rendered_glyphs(&mut self, rendered: Vec<(char, Vec<f32>)>) {
+        let max_coverage = rendered
+            .iter()
+            .flat_map(|(_, coverage)| coverage.iter().cloned())
+            .fold(0.0f32, f32::max);
+
+        for (ch, coverage) in rendered {
+            let mask = coverage
+                .iter()
+                .enumerate()
+                .filter(|&(_, &c)| c > 0.0 && c * 4.0 >= max_coverage)
+                .fold(0, |mask, (i, _)| mask | 1 << i);
+            self.glyph(ch, mask);
+        }
+    }
+
+    fn has_valid_mask_dims(&self) -> bool {
+        matches!(
+            self.mask_dims[0].checked_mul(self.mask_dims[1]),
+            Some(num_pixels) if num_pixels > 0 && num_pixels <= MAX_MASK_PIXELS
+        )
+    }
+}
+
+/// Calculate the mean coverage of each mask pixel given a coverage map of
+/// dimensions `dims`.
+fn region_coverage(coverage: &[f32], dims: [usize; 2], mask_dims: [usize; 2]) -> Vec<f32> {
+    let mut sums = vec![0.0f32; mask_dims[0] * mask_dims[1]];
+    let mut counts = vec![0usize; mask_dims[0] * mask_dims[1]];
+
+    for y in 0..dims[1] {
+        let my = y * mask_dims[1] / dims[1];
+        for x in 0..dims[0] {
+            let mx = x * mask_dims[0] / dims[0];
+            sums[mx + my * mask_dims[0]] += coverage[x + y * dims[0]];
+            counts[mx + my * mask_dims[0]] += 1;
+        }
+    }
+
+    sums.iter()
+        .zip(counts.iter())
+        .map(|(&sum, &count)| if count > 0 { sum / count as f32 } else { 0.0 })
+        .collect()
+}
+
+struct BdfFont {
+    /// `FONTBOUNDINGBOX` (width, height, x offset, y offset)
+    bbx: [i64; 4],
+    glyphs: Vec<BdfGlyph>,
+}
+
+struct BdfGlyph {
+    encoding: i64,
+    /// `BBX` (width, height, x offset, y offset)
+    bbx: [i64; 4],
+    /// The number of bytes per row in `bitmap`
+    row_len: usize,
+    bitmap: Vec<u8>,
+}
+
+impl BdfFont {
+    fn parse(text: &str) -> Result<Self, FontError> {
+        let mut bbx = None;
+        let mut glyphs = Vec::new();
+        let mut glyph: Option<BdfGlyph> = None;
+        let mut in_bitmap = false;
+
+        for (line_i, line) in text.lines().enumerate() {
+            let err = || FontError::InvalidBdf { line: line_i + 1 };
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or("");
+
+            let parse_bbx = |words: core::str::SplitWhitespace| -> Result<[i64; 4], _> {
+                let values: Vec<i64> = words
+                    .map(|x| x.parse().map_err(|_| err()))
+                    .collect::<Result<_, _>>()?;
+                match values[..] {
+                    [w, h, x, y] if w >= 0 && h >= 0 => Ok([w, h, x, y]),
+                    _ => Err(err()),
+                }
+            };
+
+            if in_bitmap {
+                if keyword == "ENDCHAR" {
+                    in_bitmap = false;
+                    glyphs.extend(glyph.take());
+                    continue;
+                }
+                let glyph = glyph.as_mut().ok_or_else(err)?;
+                if !keyword.is_ascii() || keyword.len() / 2 < glyph.row_len {
+                    return Err(err());
+                }
+                for i in 0..glyph.row_len {
+                    let byte =
+                        u8::from_str_radix(&keyword[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+                    glyph.bitmap.push(byte);
+                }
+                continue;
+            }
+
+            match keyword {
+                "FONTBOUNDINGBOX" => bbx = Some(parse_bbx(words)?),
+                "STARTCHAR" => {
+                    glyph = Some(BdfGlyph {
+                        encoding: -1,
+                        bbx: [0; 4],
+                        row_len: 0,
+                        bitmap: Vec::new(),
+                    })
+                }
+                "ENCODING" => {
+                    let glyph = glyph.as_mut().ok_or_else(err)?;
+                    glyph.encoding = words.next().and_then(|x| x.parse().ok()).ok_or_else(err)?;
+                }
+                "BBX" => {
+                    let glyph = glyph.as_mut().ok_or_else(err)?;
+                    glyph.bbx = parse_bbx(words)?;
+                    glyph.row_len = (glyph.bbx[0] as usize + 7) / 8;
+                }
+                "BITMAP" => {
+                    glyph.as_ref().ok_or_else(err)?;
+                    in_bitmap = true;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            bbx: bbx.ok_or(FontError::InvalidBdf {
+                line: text.lines().count(),
+            })?,
+            glyphs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GlyphSet;
+
+    const BDF: &str = "STARTFONT 2.1
+FONT -test
+SIZE 4 75 75
+FONTBOUNDINGBOX 4 4 0 -1
+CHARS 3
+STARTCHAR space
+ENCODING 32
+BBX 0 0 0 0
+BITMAP
+ENDCHAR
+STARTCHAR bar
+ENCODING 124
+BBX 2 4 0 -1
+BITMAP
+C0
+C0
+C0
+C0
+ENDCHAR
+STARTCHAR underscore
+ENCODING 95
+BBX 4 1 0 -1
+BITMAP
+F0
+ENDCHAR
+ENDFONT
+";
+
+    #[test]
+    fn bdf() {
+        let gs = GlyphSetBuilder::new([2, 2])
+            .add_bdf_glyphs(BDF, " |_".chars())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(gs.fragment_to_glyph(0b00_00), " ");
+        assert_eq!(gs.fragment_to_glyph(0b01_01), "|");
+        assert_eq!(gs.fragment_to_glyph(0b11_00), "_");
+    }
+
+    #[cfg(all(feature = "ab_glyph", feature = "std"))]
+    #[test]
+    fn ttf() {
+        use std::io::Read;
+
+        let mut font_data = Vec::new();
+        flate2::read::GzDecoder::new(
+            &include_bytes!("../web/vendor/fairfaxhd/FairfaxHD.ttf.gz")[..],
+        )
+        .read_to_end(&mut font_data)
+        .unwrap();
+
+        let gs = GlyphSetBuilder::new([2, 3])
+            .add_ttf_glyphs(&font_data, " █▀▄▌漢".chars())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(gs.glyph_to_mask(" "), Some(0b00_00_00));
+        assert_eq!(gs.glyph_to_mask("█"), Some(0b11_11_11));
+        // The middle row is half covered
+        assert_eq!(gs.glyph_to_mask("▀"), Some(0b00_11_11));
+        assert_eq!(gs.glyph_to_mask("▄"), Some(0b11_11_00));
+        assert_eq!(gs.glyph_to_mask("▌"), Some(0b01_01_01));
+
+        // The font doesn't have CJK characters
+        let mut num_glyphs = 0;
+        gs.for_each_glyph(&mut |glyph, _| {
+            assert_ne!(glyph, "漢");
+            num_glyphs += 1;
+        });
+        assert_eq!(num_glyphs, 5);
+    }
+
+    #[test]
+    fn bdf_invalid() {
+        assert_eq!(
+            GlyphSetBuilder::new([2, 2])
+                .add_bdf_glyphs("STARTFONT 2.1\nFONTBOUNDINGBOX 4 x 0 0\n", " ".chars())
+                .err(),
+            Some(FontError::InvalidBdf { line: 2 })
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Structured conversion output
+use alloc::vec::Vec;
+use core::{fmt, ops::Range};
+
+use crate::Fragment;
+
+/// A cell of a [`GlyphGrid`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphCell<'a> {
+    /// The index of the glyph in the glyph set (see
+    /// [`crate::GlyphSet::fragment_to_glyph_index`])
+    pub glyph_index: Option<usize>,
+    /// The glyph
+    pub glyph: &'a str,
+    /// The input fragment of the cell. If error diffusion is enabled, this is
+    /// the fragment before the adjustment.
+    pub fragment: Fragment,
+    /// The pixels covered by the cell in the input image (`[x, y]`). The
+    /// rectangles of neighboring cells intersect if the glyph set has a
+    /// non-zero mask overlap.
+    pub rect: [Range<usize>; 2],
+}
+
+/// The output of [`crate::Bmp2text::transform_to_grid`]: a two-dimensional
+/// array of [`GlyphCell`]s.
+///
+/// `Display` produces the same text as [`crate::Bmp2text::transform_and_write`]
+/// does with the default output options (e.g.,
+/// [`crate::Bmp2textOpts::line_prefix`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphGrid<'a> {
+    dims: [usize; 2],
+    cells: Vec<GlyphCell<'a>>,
+}
+
+impl<'a> GlyphGrid<'a> {
+    pub(crate) fn new(dims: [usize; 2], cells: Vec<GlyphCell<'a>>) -> Self {
+        assert_eq!(cells.len(), dims[0] * dims[1]);
+        Self { dims, cells }
+    }
+
+    /// Get the dimensions of the grid, measured in cells.
+    pub fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    /// Get the cells in row-major order.
+    pub fn cells(&self) -> &[GlyphCell<'a>] {
+        &self.cells
+    }
+
+    /// Get the cell at `[x, y]`.
+    pub fn get(&self, [x, y]: [usize; 2]) -> &GlyphCell<'a> {
+        assert!(x < self.dims[0] && y < self.dims[1]);
+        &self.cells[x + y * self.dims[0]]
+    }
+
+    /// Get the cells in the row `y`.
+    pub fn row(&self, y: usize) -> &[GlyphCell<'a>] {
+        assert!(y < self.dims[1]);
+        &self.cells[y * self.dims[0]..][..self.dims[0]]
+    }
+
+    /// Iterate over the rows.
+    pub fn rows(&self) -> impl Iterator<Item = &[GlyphCell<'a>]> + '_ {
+        (0..self.dims[1]).map(move |y| self.row(y))
+    }
+}
+
+impl fmt::Display for GlyphGrid<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows() {
+            for cell in row {
+                f.write_str(cell.glyph)?;
+            }
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+//! Construction of the fragment-to-glyph lookup table of an indexed glyph set.
+//!
+//! This module is shared with `build.rs`.
+use super::{int::BinInteger as _, Fragment};
+use alloc::{vec, vec::Vec};
+
+/// The method to choose a glyph for a fragment that doesn't exactly match any
+/// glyph's mask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphMetric {
+    /// Grow the matches outward from the exact matches by dilation and erosion
+    /// and then by clearing bits. This is what the built-in glyph sets use.
+    Mutation,
+    /// Choose the glyph with the fewest mismatching pixels.
+    Hamming,
+    /// Like `Hamming`, but weight each pixel by its distance from the cell's
+    /// edges. The inner pixels, which decide a glyph's shape, count more.
+    PositionWeighted,
+    /// Blur both the fragment and the mask with a 3x3 kernel and choose the
+    /// glyph with the smallest L2 distance. This favors glyphs that look
+    /// similar from afar, e.g., shifted strokes.
+    BlurL2,
+}
+
+impl Default for GlyphMetric {
+    fn default() -> Self {
+        Self::Mutation
+    }
+}
+
+/// Map every fragment of dimensions `mask_dims` to a glyph. Returns a table
+/// that contains the index into `masks` for each fragment.
+///
+/// `masks` must be in the standard order (LSB = upper left corner, MSB = lower
+/// right corner). If two glyphs have identical masks, the first one takes
+/// precedence. This also applies to ties in `metric`.
+pub fn fill_index(mask_dims: [usize; 2], masks: &[Fragment], metric: GlyphMetric) -> Vec<usize> {
+    let distance_fn: fn([usize; 2], &[i32], &[i32]) -> u64 = match metric {
+        GlyphMetric::Mutation => return fill_index_by_mutation(mask_dims, masks),
+        GlyphMetric::Hamming => |_, a, b| a.iter().zip(b).filter(|(a, b)| a != b).count() as u64,
+        GlyphMetric::PositionWeighted => |[w, h], a, b| {
+            (0..w * h)
+                .filter(|&i| a[i] != b[i])
+                .map(|i| {
+                    let [x, y] = [i % w, i / w];
+                    (1 + x.min(w - 1 - x) + y.min(h - 1 - y)) as u64
+                })
+                .sum()
+        },
+        GlyphMetric::BlurL2 => |_, a, b| {
+            a.iter()
+                .zip(b)
+                .map(|(&a, &b)| ((a - b) * (a - b)) as u64)
+                .sum()
+        },
+    };
+
+    // Convert a fragment to the representation compared by `distance_fn`
+    let num_pixels = mask_dims[0] * mask_dims[1];
+    let decode = |fragment: Fragment, out: &mut [i32]| {
+        for (i, out) in out.iter_mut().enumerate() {
+            *out = (fragment >> i & 1) as i32;
+        }
+        if metric == GlyphMetric::BlurL2 {
+            blur(mask_dims, out);
+        }
+    };
+
+    let mut decoded_masks = vec![0; masks.len() * num_pixels];
+    for (&mask, out) in masks.iter().zip(decoded_masks.chunks_exact_mut(num_pixels)) {
+        decode(mask, out);
+    }
+
+    let mut decoded_fragment = vec![0; num_pixels];
+    (0..1 << num_pixels)
+        .map(|fragment: usize| {
+            decode(fragment as Fragment, &mut decoded_fragment);
+            decoded_masks
+                .chunks_exact(num_pixels)
+                .enumerate()
+                .min_by_key(|(_, mask)| distance_fn(mask_dims, mask, &decoded_fragment))
+                .unwrap()
+                .0
+        })
+        .collect()
+}
+
+/// Apply a 3x3 binomial filter to an image in place. The pixels outside the
+/// image are considered zero.
+fn blur([w, h]: [usize; 2], image: &mut [i32]) {
+    let input: Vec<i32> = image.to_vec();
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = 0;
+            for (dy, ky) in [1, 2, 1].iter().enumerate() {
+                for (dx, kx) in [1, 2, 1].iter().enumerate() {
+                    let (sx, sy) = ((x + dx).wrapping_sub(1), (y + dy).wrapping_sub(1));
+                    if sx < w && sy < h {
+                        sum += input[sy * w + sx] * kx * ky;
+                    }
+                }
+            }
+            image[y * w + x] = sum;
+        }
+    }
+}
+
+/// The implementation of [`GlyphMetric::Mutation`].
+fn fill_index_by_mutation(mask_dims: [usize; 2], masks: &[Fragment]) -> Vec<usize> {
+    #[derive(Clone, Copy)]
+    struct IndexEnt {
+        glyph_i: usize,
+        distance: usize,
+    }
+    let mut index = vec![None; 1 << (mask_dims[0] * mask_dims[1])];
+
+    // Put the exact matches
+    for (glyph_i, &mask) in masks.iter().enumerate() {
+        if index[mask as usize].is_some() {
+            // If there are two glyphs with identical masks, the first one
+            // takes precedence
+            continue;
+        }
+        index[mask as usize] = Some(IndexEnt {
+            glyph_i,
+            distance: 0,
+        });
+    }
+
+    // Mutate known patterns to create lesser matches
+    let mut last_distance = 0;
+    for base_distance in 0.. {
+        let mut should_continue = false;
+
+        for mask in 0..index.len() {
+            if let Some(IndexEnt { distance, glyph_i }) = index[mask] {
+                if distance != base_distance {
+                    // Already processed by a previous iteration
+                    continue;
+                }
+
+                mutate_fragment_by_dilation_and_erosion(mask_dims, mask as Fragment, |new_mask| {
+                    if index[new_mask as usize].is_some() {
+                        return;
+                    }
+
+                    index[new_mask as usize] = Some(IndexEnt {
+                        glyph_i,
+                        distance: base_distance + 1,
+                    });
+                    should_continue = true;
+                });
+            }
+        }
+
+        if !should_continue {
+            break;
+        }
+
+        last_distance = base_distance;
+    }
+
+    // The above mutation technique doesn't cover the entire space. Now bring a
+    // bigger gun
+    for base_distance in 0.. {
+        let mut should_continue = false;
+
+        for mask in 0..index.len() {
+            if let Some(IndexEnt { distance, glyph_i }) = index[mask] {
+                if distance != base_distance {
+                    // Already processed by a previous iteration
+                    continue;
+                }
+
+                mutate_fragment_unconditional(mask_dims, mask as Fragment, |new_mask| {
+                    if index[new_mask as usize].is_some() {
+                        return;
+                    }
+
+                    index[new_mask as usize] = Some(IndexEnt {
+                        glyph_i,
+                        distance: base_distance + 1,
+                    });
+                    should_continue = true;
+                });
+            }
+        }
+
+        if !should_continue && base_distance > last_distance {
+            break;
+        }
+    }
+
+    // The above technique can only clear bits. If no glyph has a superset
+    // mask of some fragment, the fragment is still unassigned at this point.
+    // Pick the glyph with the smallest Hamming distance for such fragments.
+    index
+        .iter()
+        .enumerate()
+        .map(|(fragment, ent)| {
+            if let Some(ent) = ent {
+                ent.glyph_i
+            } else {
+                (0..masks.len())
+                    .min_by_key(|&i| (masks[i] ^ fragment as Fragment).count_ones())
+                    .unwrap()
+            }
+        })
+        .collect()
+}
+
+/// Mutate a fragment.
+fn mutate_fragment_by_dilation_and_erosion(
+    [w, h]: [usize; 2],
+    frag: Fragment,
+    mut cb: impl FnMut(Fragment),
+) {
+    let mut i = 0;
+    for y in 0..h {
+        for x in 0..w {
+            let cur = frag.get_bit(i);
+
+            // Deny dilation (the outcome is opposite - the result image will
+            // be dilated)
+            if cur {
+                continue;
+            }
+
+            // try copying this pixel to a neighboring one
+            for &(sx, sy) in &[(-1isize, 0), (1, 0), (0, -1isize), (0, 1)] {
+                let nx = x.wrapping_add(sx as usize);
+                let ny = y.wrapping_add(sy as usize);
+                if nx >= w || ny >= h {
+                    continue; // OOB
+                }
+
+                let ni = (nx + ny * w) as u32;
+                let nei = frag.get_bit(ni);
+
+                if cur == nei {
+                    continue; // no change
+                }
+
+                // mutate
+                let new_frag = frag ^ (1 << ni);
+
+                cb(new_frag);
+            }
+
+            i += 1;
+        }
+    }
+}
+
+/// Mutate a fragment differently.
+fn mutate_fragment_unconditional([w, h]: [usize; 2], frag: Fragment, mut cb: impl FnMut(Fragment)) {
+    for i in 0..w * h {
+        cb(frag & !(1 << i));
+    }
+}
@@ -0,0 +1,264 @@
+//! Glyph set definition files
+//!
+//! A glyph set definition file is a TOML document like the following:
+//!
+//! ```toml
+//! # The dimensions of masks, `[width, height]`
+//! mask_dims = [3, 3]
+//! # The number of pixels shared by adjacent cells (optional)
+//! mask_overlap = [0, 0]
+//! # The number of display columns occupied by each glyph (optional)
+//! glyph_width = 1
+//!
+//! # `[glyph, mask]` pairs. Masks are written row by row starting from the
+//! # upper left corner, and rows are separated by `_`.
+//! glyphs = [
+//!     [" ", "000_000_000"],
+//!     ["█", "111_111_111"],
+//!     ["▋", "110_110_110"],
+//! ]
+//! ```
+//!
+//! Instead of (or in addition to) writing masks by hand, the masks can be
+//! derived by rendering characters with a font:
+//!
+//! ```toml
+//! mask_dims = [2, 3]
+//! # A TrueType/OpenType (`.ttf`, `.otf`) or BDF (`.bdf`) font, optionally
+//! # gzip-compressed (`.gz`). Relative to the definition file.
+//! font = "FairfaxHD.ttf.gz"
+//! # The characters to render
+//! chars = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+//! ```
+//!
+//! The glyphs listed in `glyphs` take precedence over the rendered ones.
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+pub fn load(path: &Path) -> Result<img2text::OwnedGlyphSet> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse(&text, base_dir).with_context(|| {
+        format!(
+            "Failed to load a glyph set definition from '{}'",
+            path.display()
+        )
+    })
+}
+
+/// Parse a glyph set definition. Font paths are resolved relative to
+/// `base_dir`.
+fn parse(text: &str, base_dir: &Path) -> Result<img2text::OwnedGlyphSet> {
+    let doc: toml::Value = text.parse()?;
+    let table = doc
+        .as_table()
+        .ok_or_else(|| anyhow!("the document is not a table"))?;
+
+    for key in table.keys() {
+        if ![
+            "mask_dims",
+            "mask_overlap",
+            "glyph_width",
+            "glyphs",
+            "font",
+            "chars",
+        ]
+        .contains(&key.as_str())
+        {
+            bail!("unknown key `{}`", key);
+        }
+    }
+
+    let mask_dims = parse_dims(
+        table
+            .get("mask_dims")
+            .ok_or_else(|| anyhow!("`mask_dims` is missing"))?,
+    )
+    .context("`mask_dims` is invalid")?;
+    // Reject huge masks before `parse_mask` tries to handle them
+    match mask_dims[0].checked_mul(mask_dims[1]) {
+        Some(num_pixels) if num_pixels <= img2text::MAX_MASK_PIXELS => {}
+        _ => return Err(img2text::BuildGlyphSetError::BadMaskDims.into()),
+    }
+
+    let mask_overlap = if let Some(value) = table.get("mask_overlap") {
+        parse_dims(value).context("`mask_overlap` is invalid")?
+    } else {
+        [0, 0]
+    };
+
+    let glyph_width = match table.get("glyph_width") {
+        Some(toml::Value::Integer(x)) if *x > 0 => *x as usize,
+        Some(_) => bail!("`glyph_width` must be a positive integer"),
+        None => 1,
+    };
+
+    let glyphs = match table.get("glyphs") {
+        Some(value) => &value
+            .as_array()
+            .ok_or_else(|| anyhow!("`glyphs` must be an array"))?[..],
+        None if table.contains_key("font") => &[],
+        None => bail!("either `glyphs` or `font` must be specified"),
+    };
+
+    let mut builder = img2text::GlyphSetBuilder::new(mask_dims);
+    builder.mask_overlap(mask_overlap).glyph_width(glyph_width);
+    for (glyph_i, ent) in glyphs.iter().enumerate() {
+        let (glyph, mask) = match ent.as_array().map(|x| &x[..]) {
+            Some([toml::Value::String(glyph), toml::Value::String(mask)]) => (glyph, mask),
+            _ => bail!(
+                "glyph #{} must be a `[glyph, mask]` pair of strings",
+                glyph_i
+            ),
+        };
+        if glyph.is_empty() {
+            bail!("glyph #{} is an empty string", glyph_i);
+        }
+        let mask = parse_mask(mask, mask_dims)
+            .with_context(|| format!("the mask of glyph #{} ({:?}) is invalid", glyph_i, glyph))?;
+        builder.glyph(glyph.as_str(), mask);
+    }
+
+    match (table.get("font"), table.get("chars")) {
+        (Some(toml::Value::String(font)), Some(toml::Value::String(chars))) => {
+            let font_path = base_dir.join(font);
+            add_font_glyphs(&mut builder, &font_path, chars).with_context(|| {
+                format!("Failed to render glyphs with '{}'", font_path.display())
+            })?;
+        }
+        (None, None) => {}
+        (Some(_), Some(_)) => bail!("`font` and `chars` must be strings"),
+        (Some(_), None) => bail!("`chars` is missing"),
+        (None, Some(_)) => bail!("`chars` requires `font`"),
+    }
+
+    if !builder.glyphs().any(|(_, mask)| mask == 0) {
+        bail!(
+            "no glyph has the all-off mask (`{}`)",
+            vec!["0".repeat(mask_dims[0]); mask_dims[1]].join("_")
+        );
+    }
+
+    Ok(builder.build()?)
+}
+
+fn add_font_glyphs(
+    builder: &mut img2text::GlyphSetBuilder,
+    path: &Path,
+    chars: &str,
+) -> Result<()> {
+    let mut data = fs::read(path)?;
+    let mut path = PathBuf::from(path);
+    if path.extension().map_or(false, |x| x == "gz") {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+        data = decompressed;
+        path.set_extension("");
+    }
+
+    if path
+        .extension()
+        .map_or(false, |x| x.eq_ignore_ascii_case("bdf"))
+    {
+        let text = std::str::from_utf8(&data).context("the BDF font is not valid UTF-8")?;
+        builder.add_bdf_glyphs(text, chars.chars())?;
+    } else {
+        builder.add_ttf_glyphs(&data, chars.chars())?;
+    }
+    Ok(())
+}
+
+fn parse_dims(value: &toml::Value) -> Result<[usize; 2]> {
+    match value.as_array().map(|x| &x[..]) {
+        Some([toml::Value::Integer(x), toml::Value::Integer(y)]) if *x >= 0 && *y >= 0 => {
+            Ok([*x as usize, *y as usize])
+        }
+        _ => bail!("expected `[width, height]`"),
+    }
+}
+
+/// Parse a mask written in the visual order (e.g., `110_110_110`) and convert
+/// it to the standard order (LSB = upper left corner).
+fn parse_mask(text: &str, mask_dims: [usize; 2]) -> Result<img2text::Fragment> {
+    let rows: Vec<&str> = text.split('_').collect();
+    if rows.len() != mask_dims[1] {
+        bail!("expected {} rows, found {}", mask_dims[1], rows.len());
+    }
+
+    let mut mask = 0;
+    for (y, row) in rows.iter().enumerate() {
+        if row.chars().count() != mask_dims[0] {
+            bail!(
+                "row {} ({:?}) must be {} pixels wide, but it is {} pixels wide",
+                y,
+                row,
+                mask_dims[0],
+                row.chars().count()
+            );
+        }
+        for (x, ch) in row.chars().enumerate() {
+            match ch {
+                '0' => {}
+                '1' => mask |= 1 << (x + y * mask_dims[0]),
+                _ => bail!("row {} ({:?}) contains an invalid pixel {:?}", y, row, ch),
+            }
+        }
+    }
+
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use img2text::GlyphSet;
+
+    #[test]
+    fn parse_valid() {
+        let gs = parse(
+            r#"
+            mask_dims = [2, 2]
+            glyphs = [
+                [" ", "00_00"],
+                ["▘", "10_00"],
+                ["▗", "00_01"],
+            ]
+            "#,
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(gs.mask_dims(), [2, 2]);
+        assert_eq!(gs.mask_overlap(), [0, 0]);
+        assert_eq!(gs.glyph_width(), 1);
+        assert_eq!(gs.fragment_to_glyph(0b00_01), "▘");
+        assert_eq!(gs.fragment_to_glyph(0b10_00), "▗");
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let err = |text| format!("{:#}", parse(text, Path::new("")).unwrap_err());
+        assert!(err(r#"mask_dims = [2, 2]
+                glyphs = [[" ", "00_000"]]"#)
+        .contains("must be 2 pixels wide"));
+        assert!(err(r#"mask_dims = [2, 2]
+                glyphs = [[" ", "00"]]"#)
+        .contains("expected 2 rows"));
+        assert!(err(r#"mask_dims = [2, 2]
+                glyphs = [["█", "11_11"]]"#)
+        .contains("all-off mask (`00_00`)"));
+        assert!(err(r#"mask_dims = [2, 2]
+                glyphs = [[" ", "00_0x"]]"#)
+        .contains("invalid pixel"));
+        assert!(err(r#"mask_dims = [2, 2]
+                glyph = []"#)
+        .contains("unknown key `glyph`"));
+        assert!(err(r#"mask_dims = [2, 2]
+                font = "font.bdf""#)
+        .contains("`chars` is missing"));
+    }
+}
@@ -3,12 +3,65 @@ use crate::Fragment;
 /// A set of output glyphs (string fragments) that are associated with
 /// expected input image patterns.
 ///
+/// Glyph sets are `Send + Sync` so that a conversion can be split across
+/// threads (see `par_transform_lines`).
+///
 /// **This trait's methods are exempt from the API stability guarantee.**
-pub trait GlyphSet {
+pub trait GlyphSet: Send + Sync {
     fn mask_dims(&self) -> [usize; 2];
     fn mask_overlap(&self) -> [usize; 2];
     fn fragment_to_glyph(&self, fragment: Fragment) -> &str;
     fn max_glyph_len(&self) -> usize;
+
+    /// Get the number of display columns occupied by each glyph. All glyphs
+    /// in a glyph set must have the same width.
+    ///
+    /// The default implementation returns `1`.
+    fn glyph_width(&self) -> usize {
+        1
+    }
+
+    /// Find the glyph for a cell of luma values (`mask_dims[0] *
+    /// mask_dims[1]` elements in row-major order).
+    ///
+    /// The default implementation thresholds each value at the midpoint and
+    /// calls [`Self::fragment_to_glyph`].
+    fn luma_cell_to_glyph(&self, luma: &[u8]) -> &str {
+        let fragment = luma.iter().enumerate().fold(0, |fragment, (i, &x)| {
+            fragment | ((x >= 128) as Fragment) << i
+        });
+        self.fragment_to_glyph(fragment)
+    }
+
+    /// Get the mask (in the standard order) that `glyph` exactly represents.
+    /// Returns `None` if `glyph` is not in this glyph set or doesn't represent
+    /// a specific pattern. If `glyph` represents multiple masks, returns the
+    /// first one in the definition order.
+    ///
+    /// The default implementation always returns `None`.
+    fn glyph_to_mask(&self, glyph: &str) -> Option<Fragment> {
+        let _ = glyph;
+        None
+    }
+
+    /// Call `f` with each glyph and its mask in the definition order.
+    ///
+    /// The default implementation does nothing, which is appropriate for
+    /// glyph sets that are not defined by a list of glyphs.
+    fn for_each_glyph(&self, f: &mut dyn FnMut(&str, Fragment)) {
+        let _ = f;
+    }
+
+    /// Get the index of the glyph for `fragment`. For glyph sets defined by a
+    /// list of glyphs, this is the glyph's position in the definition order
+    /// (see [`Self::for_each_glyph`]). Fragments mapped to the same glyph get
+    /// the same index.
+    ///
+    /// The default implementation always returns `None`.
+    fn fragment_to_glyph_index(&self, fragment: Fragment) -> Option<usize> {
+        let _ = fragment;
+        None
+    }
 }
 
 include!(concat!(env!("OUT_DIR"), "/glyphsets.rs"));
@@ -17,7 +70,11 @@ struct IndexedGlyphSet {
     mask_dims: [usize; 2],
     mask_overlap: [usize; 2],
     max_glyph_len: usize,
-    index: &'static [&'static str],
+    glyph_width: usize,
+    /// The glyph index for each fragment
+    index: &'static [u16],
+    /// The glyphs and their masks in the definition order
+    glyphs: &'static [(&'static str, Fragment)],
 }
 
 impl GlyphSet for IndexedGlyphSet {
@@ -30,21 +87,76 @@ impl GlyphSet for IndexedGlyphSet {
     }
 
     fn fragment_to_glyph(&self, fragment: u64) -> &str {
-        self.index[fragment as usize]
+        self.glyphs[self.index[fragment as usize] as usize].0
+    }
+
+    fn fragment_to_glyph_index(&self, fragment: Fragment) -> Option<usize> {
+        Some(self.index[fragment as usize] as usize)
     }
 
     fn max_glyph_len(&self) -> usize {
         self.max_glyph_len
     }
+
+    fn glyph_width(&self) -> usize {
+        self.glyph_width
+    }
+
+    fn glyph_to_mask(&self, glyph: &str) -> Option<Fragment> {
+        self.glyphs
+            .iter()
+            .find(|&&(g, _)| g == glyph)
+            .map(|&(_, mask)| mask)
+    }
+
+    fn for_each_glyph(&self, f: &mut dyn FnMut(&str, Fragment)) {
+        for &(glyph, mask) in self.glyphs {
+            f(glyph, mask);
+        }
+    }
+}
+
+/// An ASCII density ramp. Each glyph represents the average intensity of a
+/// 2x4 cell.
+pub const GLYPH_SET_RAMP: &dyn GlyphSet = &RampGlyphSet {
+    mask_dims: [2, 4],
+    ramp: &[" ", ".", ":", "-", "=", "+", "*", "#", "%", "@"],
+    max_glyph_len: 1,
+};
+
+/// A density ramp made of shade characters. Each glyph represents the average
+/// intensity of a 2x4 cell.
+pub const GLYPH_SET_SHADE: &dyn GlyphSet = &RampGlyphSet {
+    mask_dims: [2, 4],
+    ramp: &[" ", "░", "▒", "▓", "█"],
+    max_glyph_len: "█".len(),
+};
+
+/// A glyph set keyed on the coverage (the ratio of set pixels) or the average
+/// intensity of a cell.
+struct RampGlyphSet {
+    mask_dims: [usize; 2],
+    /// Glyphs in increasing intensity order
+    ramp: &'static [&'static str],
+    max_glyph_len: usize,
 }
 
-pub const GLYPH_SET_BRAILLE8: &dyn GlyphSet = &Braille8GlyphSet(());
+impl RampGlyphSet {
+    /// Get the index in `ramp` for the intensity `x / max`.
+    fn level_for_intensity(&self, x: usize, max: usize) -> usize {
+        let num_levels = self.ramp.len() - 1;
+        (x * num_levels + max / 2) / max
+    }
 
-struct Braille8GlyphSet(());
+    /// Get the glyph for the intensity `x / max`.
+    fn glyph_for_intensity(&self, x: usize, max: usize) -> &str {
+        self.ramp[self.level_for_intensity(x, max)]
+    }
+}
 
-impl GlyphSet for Braille8GlyphSet {
+impl GlyphSet for RampGlyphSet {
     fn mask_dims(&self) -> [usize; 2] {
-        [2, 4]
+        self.mask_dims
     }
 
     fn mask_overlap(&self) -> [usize; 2] {
@@ -52,58 +164,218 @@ impl GlyphSet for Braille8GlyphSet {
     }
 
     fn fragment_to_glyph(&self, fragment: u64) -> &str {
-        let pats = "⠀⠁⠂⠃⠄⠅⠆⠇⠈⠉⠊⠋⠌⠍⠎⠏\
-            ⠐⠑⠒⠓⠔⠕⠖⠗⠘⠙⠚⠛⠜⠝⠞⠟\
-            ⠠⠡⠢⠣⠤⠥⠦⠧⠨⠩⠪⠫⠬⠭⠮⠯\
-            ⠰⠱⠲⠳⠴⠵⠶⠷⠸⠹⠺⠻⠼⠽⠾⠿\
-            ⡀⡁⡂⡃⡄⡅⡆⡇⡈⡉⡊⡋⡌⡍⡎⡏\
-            ⡐⡑⡒⡓⡔⡕⡖⡗⡘⡙⡚⡛⡜⡝⡞⡟\
-            ⡠⡡⡢⡣⡤⡥⡦⡧⡨⡩⡪⡫⡬⡭⡮⡯\
-            ⡰⡱⡲⡳⡴⡵⡶⡷⡸⡹⡺⡻⡼⡽⡾⡿\
-            ⢀⢁⢂⢃⢄⢅⢆⢇⢈⢉⢊⢋⢌⢍⢎⢏\
-            ⢐⢑⢒⢓⢔⢕⢖⢗⢘⢙⢚⢛⢜⢝⢞⢟\
-            ⢠⢡⢢⢣⢤⢥⢦⢧⢨⢩⢪⢫⢬⢭⢮⢯\
-            ⢰⢱⢲⢳⢴⢵⢶⢷⢸⢹⢺⢻⢼⢽⢾⢿\
-            ⣀⣁⣂⣃⣄⣅⣆⣇⣈⣉⣊⣋⣌⣍⣎⣏\
-            ⣐⣑⣒⣓⣔⣕⣖⣗⣘⣙⣚⣛⣜⣝⣞⣟\
-            ⣠⣡⣢⣣⣤⣥⣦⣧⣨⣩⣪⣫⣬⣭⣮⣯\
-            ⣰⣱⣲⣳⣴⣵⣶⣷⣸⣹⣺⣻⣼⣽⣾⣿";
-        // ISO/TR 11548-1 dot numbering      Our fragment bit positions:
-        // (mapped to bit positions of
-        // Unicode code points):
-        //
-        //             0  3                             0  1
-        //             1  4                             2  3
-        //             2  5                             4  5
-        //             6  7                             6  7
-        //
-        // Notice that only the positions 1–4 differ between them. Therefore, we
-        // use a 16x4-bit LUT to remap these bits.
-        const LUT: u64 = {
-            let mut lut = 0u64;
-            let mut i = 0;
-            while i < 16 {
-                let b1 = i & 0b0001;
-                let b2 = i & 0b0010;
-                let b3 = i & 0b0100;
-                let b4 = i & 0b1000;
-                let uni_b4321 = (b1 << 2) | (b2 >> 1) | (b3 << 1) | (b4 >> 2);
-                lut |= uni_b4321 << (i * 4);
-                i += 1;
+        self.glyph_for_intensity(
+            fragment.count_ones() as usize,
+            self.mask_dims[0] * self.mask_dims[1],
+        )
+    }
+
+    fn max_glyph_len(&self) -> usize {
+        self.max_glyph_len
+    }
+
+    fn luma_cell_to_glyph(&self, luma: &[u8]) -> &str {
+        let sum: usize = luma.iter().map(|&x| x as usize).sum();
+        self.glyph_for_intensity(sum, luma.len() * 255)
+    }
+
+    fn fragment_to_glyph_index(&self, fragment: Fragment) -> Option<usize> {
+        Some(self.level_for_intensity(
+            fragment.count_ones() as usize,
+            self.mask_dims[0] * self.mask_dims[1],
+        ))
+    }
+}
+
+pub const GLYPH_SET_BRAILLE8: &dyn GlyphSet = &BrailleGlyphSet::eight_dot(BrailleBlank::Pattern);
+
+pub const GLYPH_SET_BRAILLE6: &dyn GlyphSet = &BrailleGlyphSet::six_dot(BrailleBlank::Pattern);
+
+/// Specifies how [`BrailleGlyphSet`] represents a cell with no dots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrailleBlank {
+    /// The blank braille pattern (U+2800 `⠀`)
+    Pattern,
+    /// An ASCII space
+    Space,
+    /// The braille pattern with only the dot 3 (U+2804 `⠄`)
+    ///
+    /// This makes blank cells indistinguishable from the cells with only the
+    /// dot 3. [`GlyphSet::glyph_to_mask`] maps `⠄` to the empty mask, so
+    /// `text2bmp` drops such dots and [`cell_error`](crate::cell_error)
+    /// reports an error for them even though they are reproduced exactly.
+    /// The `trim_trailing_whitespace` and `strip_blank_rows` options of
+    /// [`Bmp2textOpts`](crate::Bmp2textOpts) don't treat `⠄` as blank.
+    Dot,
+}
+
+/// A glyph set of braille patterns, either in the eight-dot form (2x4) or the
+/// six-dot form (2x3).
+///
+/// [`GLYPH_SET_BRAILLE8`] and [`GLYPH_SET_BRAILLE6`] are instances of this type
+/// using [`BrailleBlank::Pattern`].
+#[derive(Debug, Clone, Copy)]
+pub struct BrailleGlyphSet {
+    six_dot: bool,
+    blank: BrailleBlank,
+}
+
+impl BrailleGlyphSet {
+    pub const fn eight_dot(blank: BrailleBlank) -> Self {
+        Self {
+            six_dot: false,
+            blank,
+        }
+    }
+
+    pub const fn six_dot(blank: BrailleBlank) -> Self {
+        Self {
+            six_dot: true,
+            blank,
+        }
+    }
+
+    /// Get the offset of the braille pattern for `fragment` from U+2800.
+    fn fragment_to_uni(&self, fragment: Fragment) -> u64 {
+        if self.six_dot {
+            six_dot_fragment_to_uni(fragment)
+        } else {
+            eight_dot_fragment_to_uni(fragment)
+        }
+    }
+}
+
+/// All 256 braille patterns (U+2800–U+28FF)
+const BRAILLE_PATTERNS: &str = "⠀⠁⠂⠃⠄⠅⠆⠇⠈⠉⠊⠋⠌⠍⠎⠏\
+    ⠐⠑⠒⠓⠔⠕⠖⠗⠘⠙⠚⠛⠜⠝⠞⠟\
+    ⠠⠡⠢⠣⠤⠥⠦⠧⠨⠩⠪⠫⠬⠭⠮⠯\
+    ⠰⠱⠲⠳⠴⠵⠶⠷⠸⠹⠺⠻⠼⠽⠾⠿\
+    ⡀⡁⡂⡃⡄⡅⡆⡇⡈⡉⡊⡋⡌⡍⡎⡏\
+    ⡐⡑⡒⡓⡔⡕⡖⡗⡘⡙⡚⡛⡜⡝⡞⡟\
+    ⡠⡡⡢⡣⡤⡥⡦⡧⡨⡩⡪⡫⡬⡭⡮⡯\
+    ⡰⡱⡲⡳⡴⡵⡶⡷⡸⡹⡺⡻⡼⡽⡾⡿\
+    ⢀⢁⢂⢃⢄⢅⢆⢇⢈⢉⢊⢋⢌⢍⢎⢏\
+    ⢐⢑⢒⢓⢔⢕⢖⢗⢘⢙⢚⢛⢜⢝⢞⢟\
+    ⢠⢡⢢⢣⢤⢥⢦⢧⢨⢩⢪⢫⢬⢭⢮⢯\
+    ⢰⢱⢲⢳⢴⢵⢶⢷⢸⢹⢺⢻⢼⢽⢾⢿\
+    ⣀⣁⣂⣃⣄⣅⣆⣇⣈⣉⣊⣋⣌⣍⣎⣏\
+    ⣐⣑⣒⣓⣔⣕⣖⣗⣘⣙⣚⣛⣜⣝⣞⣟\
+    ⣠⣡⣢⣣⣤⣥⣦⣧⣨⣩⣪⣫⣬⣭⣮⣯\
+    ⣰⣱⣲⣳⣴⣵⣶⣷⣸⣹⣺⣻⣼⣽⣾⣿";
+
+impl GlyphSet for BrailleGlyphSet {
+    fn mask_dims(&self) -> [usize; 2] {
+        if self.six_dot {
+            [2, 3]
+        } else {
+            [2, 4]
+        }
+    }
+
+    fn mask_overlap(&self) -> [usize; 2] {
+        [0, 0]
+    }
+
+    fn fragment_to_glyph(&self, fragment: u64) -> &str {
+        let uni = self.fragment_to_uni(fragment);
+
+        if uni == 0 {
+            match self.blank {
+                BrailleBlank::Pattern => {}
+                BrailleBlank::Space => return " ",
+                BrailleBlank::Dot => return "⠄",
             }
-            lut.rotate_left(1)
-        };
+        }
 
-        let uni_b7650 = fragment & 0b11100001;
-        let b4321 = (fragment & 0b00011110) >> 1;
-        let uni_b4321 = LUT.rotate_right((b4321 * 4) as _) & 0b11110;
-        let uni = uni_b7650 | uni_b4321;
-        &pats[uni as usize * 3..uni as usize * 3 + 3]
+        &BRAILLE_PATTERNS[uni as usize * 3..uni as usize * 3 + 3]
     }
 
     fn max_glyph_len(&self) -> usize {
         "⣿".len()
     }
+
+    fn glyph_to_mask(&self, glyph: &str) -> Option<Fragment> {
+        if glyph == self.fragment_to_glyph(0) {
+            return Some(0);
+        }
+
+        let mut chars = glyph.chars();
+        let uni = (chars.next()? as u64).checked_sub(0x2800)?;
+        let num_pixels = if self.six_dot { 6 } else { 8 };
+        // U+2800 is only valid as the blank glyph, which is handled above
+        if chars.next().is_some() || uni == 0 || uni >= 1 << num_pixels {
+            return None;
+        }
+        Some(uni_to_fragment(uni))
+    }
+
+    /// Returns the offset of the braille pattern from U+2800 (`0` for the
+    /// blank glyph regardless of [`BrailleBlank`]).
+    fn fragment_to_glyph_index(&self, fragment: Fragment) -> Option<usize> {
+        Some(self.fragment_to_uni(fragment) as usize)
+    }
+}
+
+/// Convert a 2x4 fragment to the offset of the corresponding braille pattern
+/// from U+2800.
+fn eight_dot_fragment_to_uni(fragment: u64) -> u64 {
+    // ISO/TR 11548-1 dot numbering      Our fragment bit positions:
+    // (mapped to bit positions of
+    // Unicode code points):
+    //
+    //             0  3                             0  1
+    //             1  4                             2  3
+    //             2  5                             4  5
+    //             6  7                             6  7
+    //
+    // Notice that only the positions 1–4 differ between them. Therefore, we
+    // use a 16x4-bit LUT to remap these bits.
+    const LUT: u64 = {
+        let mut lut = 0u64;
+        let mut i = 0;
+        while i < 16 {
+            let b1 = i & 0b0001;
+            let b2 = i & 0b0010;
+            let b3 = i & 0b0100;
+            let b4 = i & 0b1000;
+            let uni_b4321 = (b1 << 2) | (b2 >> 1) | (b3 << 1) | (b4 >> 2);
+            lut |= uni_b4321 << (i * 4);
+            i += 1;
+        }
+        lut.rotate_left(1)
+    };
+
+    let uni_b7650 = fragment & 0b11100001;
+    let b4321 = (fragment & 0b00011110) >> 1;
+    let uni_b4321 = LUT.rotate_right((b4321 * 4) as _) & 0b11110;
+    uni_b7650 | uni_b4321
+}
+
+/// Convert the offset of a braille pattern from U+2800 to the corresponding
+/// 2x4 fragment. This is the inverse of [`eight_dot_fragment_to_uni`] and, for
+/// six-dot patterns, [`six_dot_fragment_to_uni`].
+fn uni_to_fragment(uni: u64) -> Fragment {
+    // See `eight_dot_fragment_to_uni` for the bit positions
+    const UNI_BIT: [u32; 8] = [0, 3, 1, 4, 2, 5, 6, 7];
+    (0..8)
+        .filter(|&i| uni & (1 << UNI_BIT[i]) != 0)
+        .fold(0, |fragment, i| fragment | 1 << i)
+}
+
+/// Convert a 2x3 fragment to the offset of the corresponding braille pattern
+/// from U+2800.
+fn six_dot_fragment_to_uni(fragment: u64) -> u64 {
+    // ISO/TR 11548-1 dot numbering      Our fragment bit positions:
+    // (mapped to bit positions of
+    // Unicode code points):
+    //
+    //             0  3                             0  1
+    //             1  4                             2  3
+    //             2  5                             4  5
+    const UNI_BIT: [u32; 6] = [0, 3, 1, 4, 2, 5];
+    (0..6)
+        .filter(|&i| fragment & (1 << i) != 0)
+        .fold(0, |uni, i| uni | 1 << UNI_BIT[i])
 }
 
 #[cfg(test)]
@@ -117,4 +389,142 @@ mod tests {
         assert_eq!(gs.fragment_to_glyph(0b10_01_01_01), "⢇");
         assert_eq!(gs.fragment_to_glyph(0b11_11_11_11), "⣿");
     }
+
+    #[test]
+    fn braille6() {
+        let gs = GLYPH_SET_BRAILLE6;
+        assert_eq!(gs.mask_dims(), [2, 3]);
+        assert_eq!(gs.fragment_to_glyph(0), "⠀");
+        assert_eq!(gs.fragment_to_glyph(0b01_01_01), "⠇");
+        assert_eq!(gs.fragment_to_glyph(0b10_00_11), "⠩");
+        assert_eq!(gs.fragment_to_glyph(0b11_11_11), "⠿");
+    }
+
+    #[test]
+    fn braille_glyph_to_mask() {
+        for &six_dot in &[false, true] {
+            let num_pixels = if six_dot { 6 } else { 8 };
+            for &blank in &[BrailleBlank::Pattern, BrailleBlank::Space] {
+                let gs = if six_dot {
+                    BrailleGlyphSet::six_dot(blank)
+                } else {
+                    BrailleGlyphSet::eight_dot(blank)
+                };
+                for fragment in 0..1 << num_pixels {
+                    assert_eq!(
+                        gs.glyph_to_mask(gs.fragment_to_glyph(fragment)),
+                        Some(fragment)
+                    );
+                }
+                assert_eq!(gs.glyph_to_mask("a"), None);
+            }
+        }
+        assert_eq!(GLYPH_SET_BRAILLE6.glyph_to_mask("⣿"), None);
+        assert_eq!(
+            BrailleGlyphSet::eight_dot(BrailleBlank::Space).glyph_to_mask("⠀"),
+            None
+        );
+    }
+
+    #[test]
+    fn braille_blank() {
+        for &(blank, glyph) in &[
+            (BrailleBlank::Pattern, "⠀"),
+            (BrailleBlank::Space, " "),
+            (BrailleBlank::Dot, "⠄"),
+        ] {
+            assert_eq!(
+                BrailleGlyphSet::eight_dot(blank).fragment_to_glyph(0),
+                glyph
+            );
+            assert_eq!(BrailleGlyphSet::six_dot(blank).fragment_to_glyph(0), glyph);
+        }
+        assert_eq!(
+            BrailleGlyphSet::six_dot(BrailleBlank::Dot).fragment_to_glyph(0b00_00_01),
+            "⠁"
+        );
+    }
+
+    #[test]
+    fn octant() {
+        let gs = GLYPH_SET_OCTANT;
+        assert_eq!(gs.fragment_to_glyph(0), " ");
+        assert_eq!(gs.fragment_to_glyph(0b00_00_00_01), "\u{1CEA8}");
+        assert_eq!(gs.fragment_to_glyph(0b00_00_01_01), "▘");
+        assert_eq!(gs.fragment_to_glyph(0b10_01_01_01), "\u{1CD85}");
+        assert_eq!(gs.fragment_to_glyph(0b11_11_10_10), "▟");
+        assert_eq!(gs.fragment_to_glyph(0b11_11_11_11), "█");
+
+        // Every fragment has an exact match
+        let glyphs: std::collections::HashSet<_> =
+            (0..256).map(|x| gs.fragment_to_glyph(x)).collect();
+        assert_eq!(glyphs.len(), 256);
+    }
+
+    #[test]
+    fn line() {
+        let gs = GLYPH_SET_LINE;
+        assert_eq!(gs.fragment_to_glyph(0b000_111_000), "─");
+        assert_eq!(gs.fragment_to_glyph(0b001_001_001), "│");
+        // Corners along the cell boundary
+        assert_eq!(gs.fragment_to_glyph(0b001_001_111), "┌");
+        assert_eq!(gs.fragment_to_glyph(0b111_100_100), "┘");
+        assert_eq!(GLYPH_SET_LINE_DOUBLE.fragment_to_glyph(0b010_111_010), "╬");
+    }
+
+    #[test]
+    fn ascii() {
+        let gs = GLYPH_SET_ASCII;
+        assert!((0..512).all(|x| gs.fragment_to_glyph(x).is_ascii()));
+        assert_eq!(gs.fragment_to_glyph(0b100_010_001), "\\");
+        assert_eq!(gs.fragment_to_glyph(0b000_111_000), "-");
+        assert_eq!(gs.fragment_to_glyph(0b111_000_000), "_");
+    }
+
+    #[test]
+    fn ramp() {
+        let gs = GLYPH_SET_SHADE;
+        assert_eq!(gs.fragment_to_glyph(0), " ");
+        assert_eq!(gs.fragment_to_glyph(0b00_00_11_11), "▒");
+        assert_eq!(gs.fragment_to_glyph(0b11_11_11_11), "█");
+        assert_eq!(gs.luma_cell_to_glyph(&[0; 8]), " ");
+        assert_eq!(gs.luma_cell_to_glyph(&[64; 8]), "░");
+        assert_eq!(gs.luma_cell_to_glyph(&[255; 8]), "█");
+        assert_eq!(GLYPH_SET_RAMP.luma_cell_to_glyph(&[255; 8]), "@");
+    }
+
+    #[test]
+    fn glyph_index() {
+        for &gs in &[GLYPH_SET_SLC, GLYPH_SET_MS_2X3, GLYPH_SET_ASCII] {
+            let mut glyphs = Vec::new();
+            gs.for_each_glyph(&mut |glyph, _| glyphs.push(glyph.to_owned()));
+            let [w, h] = gs.mask_dims();
+            for fragment in 0..1 << (w * h) {
+                let i = gs.fragment_to_glyph_index(fragment).unwrap();
+                assert_eq!(glyphs[i], gs.fragment_to_glyph(fragment));
+            }
+        }
+
+        assert_eq!(
+            GLYPH_SET_SHADE.fragment_to_glyph_index(0b00_00_11_11),
+            Some(2)
+        );
+        assert_eq!(
+            GLYPH_SET_BRAILLE8.fragment_to_glyph_index(0b11_11_11_11),
+            Some(255)
+        );
+        assert_eq!(
+            BrailleGlyphSet::eight_dot(BrailleBlank::Space).fragment_to_glyph_index(0),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn luma_threshold() {
+        let gs = GLYPH_SET_BRAILLE8;
+        assert_eq!(
+            gs.luma_cell_to_glyph(&[255, 0, 0, 200, 128, 0, 0, 127]),
+            gs.fragment_to_glyph(0b00_01_10_01)
+        );
+    }
 }
@@ -1,4 +1,14 @@
 //! Input image representation
+//!
+//! This module also provides [`ImageRead`] adapters for common pixel buffer
+//! layouts. With the `image` feature, the traits are also implemented for
+//! [`::image::GrayImage`], [`::image::RgbImage`], and
+//! [`::image::DynamicImage`].
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
+use core::ops::Range;
+
 use crate::{Span, SPAN_BITS};
 
 pub trait ImageRead {
@@ -14,6 +24,63 @@ pub trait ImageRead {
     fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]);
 }
 
+/// Provides the luma (brightness) values of an input image, for glyph sets
+/// that represent intensity levels rather than bit patterns.
+pub trait LumaImageRead {
+    /// Get the image's dimensions as `[width, height]`.
+    fn dims(&self) -> [usize; 2];
+
+    /// Copy the luma values of the pixels in the specified row to `out`. The
+    /// value `255` represents a fully "on" pixel.
+    ///
+    /// `out` contains exactly `self.dims()[0]` elements.
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]);
+}
+
+/// An RGB color.
+pub type Rgb = [u8; 3];
+
+/// Provides the colors of an input image for colored output.
+pub trait ColorImageRead {
+    /// Get the image's dimensions as `[width, height]`.
+    fn dims(&self) -> [usize; 2];
+
+    /// Copy the colors of the pixels in the specified row to `out`.
+    ///
+    /// `out` contains exactly `self.dims()[0]` elements.
+    fn copy_line_as_rgb_to(&self, y: usize, out: &mut [Rgb]);
+}
+
+impl<T: ImageRead + ?Sized> ImageRead for &T {
+    fn dims(&self) -> [usize; 2] {
+        (**self).dims()
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        (**self).copy_line_as_spans_to(y, out)
+    }
+}
+
+impl<T: LumaImageRead + ?Sized> LumaImageRead for &T {
+    fn dims(&self) -> [usize; 2] {
+        (**self).dims()
+    }
+
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]) {
+        (**self).copy_line_as_luma_to(y, out)
+    }
+}
+
+impl<T: ColorImageRead + ?Sized> ColorImageRead for &T {
+    fn dims(&self) -> [usize; 2] {
+        (**self).dims()
+    }
+
+    fn copy_line_as_rgb_to(&self, y: usize, out: &mut [Rgb]) {
+        (**self).copy_line_as_rgb_to(y, out)
+    }
+}
+
 /// Fill `[Span]` using a given function that returns the desired value for each
 /// bit.
 pub fn set_spans_by_fn(
@@ -40,3 +107,569 @@ pub fn set_spans_by_fn(
         out_spans[num_pixels / SPAN_BITS] = b;
     }
 }
+
+/// The order of pixels in each byte of [`BitmapImageRead`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The most significant bit represents the leftmost pixel (e.g., PBM,
+    /// BMP).
+    MsbFirst,
+    /// The least significant bit represents the leftmost pixel (e.g., XBM).
+    LsbFirst,
+}
+
+/// An [`ImageRead`] adapter for a packed 1-bit-per-pixel bitmap. Set bits
+/// represent "on" pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct BitmapImageRead<'a> {
+    data: &'a [u8],
+    dims: [usize; 2],
+    stride: usize,
+    bit_order: BitOrder,
+}
+
+impl<'a> BitmapImageRead<'a> {
+    /// Construct a `BitmapImageRead`. `stride` is the number of bytes per row.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `stride` is too small for the width or `data`
+    /// is too short for the dimensions.
+    pub fn new(data: &'a [u8], dims: [usize; 2], stride: usize, bit_order: BitOrder) -> Self {
+        let row_len = (dims[0] + 7) / 8;
+        assert!(stride >= row_len, "stride is too small");
+        assert!(
+            dims[1] == 0 || data.len() >= (dims[1] - 1) * stride + row_len,
+            "data is too short"
+        );
+        Self {
+            data,
+            dims,
+            stride,
+            bit_order,
+        }
+    }
+}
+
+impl ImageRead for BitmapImageRead<'_> {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        let width = self.dims[0];
+        let row = &self.data[y * self.stride..][..(width + 7) / 8];
+        let num_spans = (width + SPAN_BITS - 1) / SPAN_BITS;
+
+        // Bits are packed LSB-first in `Span`
+        for (span_i, out) in out[..num_spans].iter_mut().enumerate() {
+            let mut span: Span = 0;
+            for (k, &byte) in row
+                .iter()
+                .skip(span_i * SPAN_BITS / 8)
+                .take(SPAN_BITS / 8)
+                .enumerate()
+            {
+                let byte = match self.bit_order {
+                    BitOrder::MsbFirst => byte.reverse_bits(),
+                    BitOrder::LsbFirst => byte,
+                };
+                span |= (byte as Span) << (k * 8);
+            }
+            *out = span;
+        }
+
+        // Clear the padding bits
+        if width % SPAN_BITS != 0 {
+            out[num_spans - 1] &= (1 << (width % SPAN_BITS)) - 1;
+        }
+    }
+}
+
+/// An [`ImageRead`] and [`LumaImageRead`] adapter for an 8-bit grayscale
+/// buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct GrayImageRead<'a> {
+    data: &'a [u8],
+    dims: [usize; 2],
+    stride: usize,
+    /// The pixels with values greater than or equal to this are "on".
+    /// Defaults to `128`.
+    pub threshold: u8,
+    /// Treat dark pixels as "on". Also inverts the luma values passed to
+    /// [`LumaImageRead`]. Defaults to `false`.
+    pub invert: bool,
+}
+
+impl<'a> GrayImageRead<'a> {
+    /// Construct a `GrayImageRead` for a tightly packed buffer.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `data` is too short for the dimensions.
+    pub fn new(data: &'a [u8], dims: [usize; 2]) -> Self {
+        Self::with_stride(data, dims, dims[0])
+    }
+
+    /// Construct a `GrayImageRead`. `stride` is the number of bytes per row.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `stride` is smaller than the width or `data`
+    /// is too short for the dimensions.
+    pub fn with_stride(data: &'a [u8], dims: [usize; 2], stride: usize) -> Self {
+        assert!(stride >= dims[0], "stride is too small");
+        assert!(
+            dims[1] == 0 || data.len() >= (dims[1] - 1) * stride + dims[0],
+            "data is too short"
+        );
+        Self {
+            data,
+            dims,
+            stride,
+            threshold: 128,
+            invert: false,
+        }
+    }
+
+    fn row(&self, y: usize) -> &'a [u8] {
+        &self.data[y * self.stride..][..self.dims[0]]
+    }
+}
+
+impl ImageRead for GrayImageRead<'_> {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        let row = self.row(y);
+        let (threshold, invert) = (self.threshold, self.invert);
+        set_spans_by_fn(out, row.len(), |x| (row[x] >= threshold) ^ invert);
+    }
+}
+
+impl LumaImageRead for GrayImageRead<'_> {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]) {
+        for (out, &luma) in out.iter_mut().zip(self.row(y)) {
+            *out = if self.invert { 255 - luma } else { luma };
+        }
+    }
+}
+
+/// Find the smallest rectangle (`[x, y]`) containing all "on" pixels of
+/// `image`, expanded by `padding` pixels on each side and clipped to the
+/// image. Returns `None` if no pixels are on.
+///
+/// This is useful to remove empty margins with [`CropImageRead`] or before
+/// fitting the image to an output size.
+#[cfg(feature = "alloc")]
+pub fn content_bbox(image: &impl ImageRead, padding: usize) -> Option<[Range<usize>; 2]> {
+    let [width, height] = image.dims();
+    let mut row = vec![0; (width + SPAN_BITS - 1) / SPAN_BITS];
+    let mut bbox: Option<[Range<usize>; 2]> = None;
+
+    for y in 0..height {
+        image.copy_line_as_spans_to(y, &mut row);
+
+        // Ignore the bits past the end of the row
+        if width % SPAN_BITS != 0 {
+            *row.last_mut().unwrap() &= (1 << (width % SPAN_BITS)) - 1;
+        }
+
+        let first = row.iter().position(|&span| span != 0);
+        let last = row.iter().rposition(|&span| span != 0);
+        if let (Some(first), Some(last)) = (first, last) {
+            let x0 = first * SPAN_BITS + row[first].trailing_zeros() as usize;
+            let x1 = (last + 1) * SPAN_BITS - row[last].leading_zeros() as usize;
+            bbox = Some(match bbox {
+                Some([xs, ys]) => [xs.start.min(x0)..xs.end.max(x1), ys.start..y + 1],
+                None => [x0..x1, y..y + 1],
+            });
+        }
+    }
+
+    bbox.map(|[xs, ys]| {
+        [
+            xs.start.saturating_sub(padding)..xs.end.saturating_add(padding).min(width),
+            ys.start.saturating_sub(padding)..ys.end.saturating_add(padding).min(height),
+        ]
+    })
+}
+
+/// An adapter that exposes a rectangular region (`[x, y]`) of an image. It
+/// implements each of [`ImageRead`], [`LumaImageRead`], and
+/// [`ColorImageRead`] that the underlying image implements.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct CropImageRead<I> {
+    image: I,
+    rect: [Range<usize>; 2],
+}
+
+#[cfg(feature = "alloc")]
+impl<I> CropImageRead<I> {
+    /// Construct a `CropImageRead`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `rect` is not within the image `dims`.
+    pub fn new(image: I, dims: [usize; 2], rect: [Range<usize>; 2]) -> Self {
+        assert!(
+            rect[0].start <= rect[0].end && rect[0].end <= dims[0],
+            "rect is out of bounds"
+        );
+        assert!(
+            rect[1].start <= rect[1].end && rect[1].end <= dims[1],
+            "rect is out of bounds"
+        );
+        Self { image, rect }
+    }
+
+    /// Get the region exposed by this adapter.
+    pub fn rect(&self) -> [Range<usize>; 2] {
+        self.rect.clone()
+    }
+
+    fn cropped_dims(&self) -> [usize; 2] {
+        [self.rect[0].len(), self.rect[1].len()]
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: ImageRead> ImageRead for CropImageRead<I> {
+    fn dims(&self) -> [usize; 2] {
+        self.cropped_dims()
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        let [width, _] = self.cropped_dims();
+        let x0 = self.rect[0].start;
+
+        // One extra element so that the loop below can read `word + 1`
+        let mut row = vec![0; (self.image.dims()[0] + SPAN_BITS - 1) / SPAN_BITS + 1];
+        self.image
+            .copy_line_as_spans_to(self.rect[1].start + y, &mut row);
+
+        let num_spans = (width + SPAN_BITS - 1) / SPAN_BITS;
+        for (i, out_span) in out[..num_spans].iter_mut().enumerate() {
+            let (word, shift) = ((x0 + i * SPAN_BITS) / SPAN_BITS, x0 % SPAN_BITS);
+            *out_span = row[word] >> shift;
+            if shift != 0 {
+                *out_span |= row[word + 1] << (SPAN_BITS - shift);
+            }
+        }
+
+        if width % SPAN_BITS != 0 {
+            out[num_spans - 1] &= (1 << (width % SPAN_BITS)) - 1;
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: LumaImageRead> LumaImageRead for CropImageRead<I> {
+    fn dims(&self) -> [usize; 2] {
+        self.cropped_dims()
+    }
+
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]) {
+        let mut row = vec![0; self.image.dims()[0]];
+        self.image
+            .copy_line_as_luma_to(self.rect[1].start + y, &mut row);
+        out.copy_from_slice(&row[self.rect[0].clone()]);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: ColorImageRead> ColorImageRead for CropImageRead<I> {
+    fn dims(&self) -> [usize; 2] {
+        self.cropped_dims()
+    }
+
+    fn copy_line_as_rgb_to(&self, y: usize, out: &mut [Rgb]) {
+        let mut row = vec![[0; 3]; self.image.dims()[0]];
+        self.image
+            .copy_line_as_rgb_to(self.rect[1].start + y, &mut row);
+        out.copy_from_slice(&row[self.rect[0].clone()]);
+    }
+}
+
+/// The formula to calculate the luma value of an RGB color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LumaFormula {
+    /// ITU-R BT.601 (`0.299 R + 0.587 G + 0.114 B`)
+    Rec601,
+    /// ITU-R BT.709 (`0.2126 R + 0.7152 G + 0.0722 B`)
+    Rec709,
+    /// The unweighted average of the three channels
+    Average,
+}
+
+impl LumaFormula {
+    pub fn luma(self, [r, g, b]: Rgb) -> u8 {
+        let [r, g, b] = [r as u32, g as u32, b as u32];
+        match self {
+            // The coefficients are scaled by 2^16
+            Self::Rec601 => ((r * 19595 + g * 38470 + b * 7471 + 0x8000) >> 16) as u8,
+            Self::Rec709 => ((r * 13933 + g * 46871 + b * 4732 + 0x8000) >> 16) as u8,
+            Self::Average => ((r + g + b + 1) / 3) as u8,
+        }
+    }
+}
+
+/// An [`ImageRead`], [`LumaImageRead`], and [`ColorImageRead`] adapter for an
+/// 8-bit RGBA buffer. The alpha channel is ignored.
+#[derive(Debug, Clone, Copy)]
+pub struct RgbaImageRead<'a> {
+    data: &'a [u8],
+    dims: [usize; 2],
+    stride: usize,
+    /// The formula to convert pixels to luma values. Defaults to
+    /// [`LumaFormula::Rec601`].
+    pub luma_formula: LumaFormula,
+    /// The pixels with luma values greater than or equal to this are "on".
+    /// Defaults to `128`.
+    pub threshold: u8,
+    /// Treat dark pixels as "on". Also inverts the luma values passed to
+    /// [`LumaImageRead`]. Defaults to `false`.
+    pub invert: bool,
+}
+
+impl<'a> RgbaImageRead<'a> {
+    /// Construct a `RgbaImageRead` for a tightly packed buffer.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `data` is too short for the dimensions.
+    pub fn new(data: &'a [u8], dims: [usize; 2]) -> Self {
+        Self::with_stride(data, dims, dims[0] * 4)
+    }
+
+    /// Construct a `RgbaImageRead`. `stride` is the number of bytes per row.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `stride` is too small for the width or `data`
+    /// is too short for the dimensions.
+    pub fn with_stride(data: &'a [u8], dims: [usize; 2], stride: usize) -> Self {
+        assert!(stride >= dims[0] * 4, "stride is too small");
+        assert!(
+            dims[1] == 0 || data.len() >= (dims[1] - 1) * stride + dims[0] * 4,
+            "data is too short"
+        );
+        Self {
+            data,
+            dims,
+            stride,
+            luma_formula: LumaFormula::Rec601,
+            threshold: 128,
+            invert: false,
+        }
+    }
+
+    fn pixels(&self, y: usize) -> impl Iterator<Item = Rgb> + 'a {
+        self.data[y * self.stride..][..self.dims[0] * 4]
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2]])
+    }
+}
+
+impl ImageRead for RgbaImageRead<'_> {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        let mut pixels = self.pixels(y);
+        set_spans_by_fn(out, self.dims[0], |_| {
+            (self.luma_formula.luma(pixels.next().unwrap()) >= self.threshold) ^ self.invert
+        });
+    }
+}
+
+impl LumaImageRead for RgbaImageRead<'_> {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]) {
+        for (out, rgb) in out.iter_mut().zip(self.pixels(y)) {
+            let luma = self.luma_formula.luma(rgb);
+            *out = if self.invert { 255 - luma } else { luma };
+        }
+    }
+}
+
+impl ColorImageRead for RgbaImageRead<'_> {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_rgb_to(&self, y: usize, out: &mut [Rgb]) {
+        for (out, rgb) in out.iter_mut().zip(self.pixels(y)) {
+            *out = rgb;
+        }
+    }
+}
+
+/// Pixels with values greater than or equal to `128` are "on".
+#[cfg(feature = "image")]
+impl ImageRead for ::image::GrayImage {
+    fn dims(&self) -> [usize; 2] {
+        [self.width() as usize, self.height() as usize]
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        GrayImageRead::new(self.as_raw(), ImageRead::dims(self)).copy_line_as_spans_to(y, out)
+    }
+}
+
+#[cfg(feature = "image")]
+impl LumaImageRead for ::image::GrayImage {
+    fn dims(&self) -> [usize; 2] {
+        [self.width() as usize, self.height() as usize]
+    }
+
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]) {
+        let [width, _] = LumaImageRead::dims(self);
+        out.copy_from_slice(&self.as_raw()[y * width..][..width]);
+    }
+}
+
+#[cfg(feature = "image")]
+impl ColorImageRead for ::image::RgbImage {
+    fn dims(&self) -> [usize; 2] {
+        [self.width() as usize, self.height() as usize]
+    }
+
+    fn copy_line_as_rgb_to(&self, y: usize, out: &mut [Rgb]) {
+        for (x, out) in out.iter_mut().enumerate() {
+            *out = self[(x as u32, y as u32)].0;
+        }
+    }
+}
+
+/// Pixels with luma values greater than or equal to `128` are "on".
+#[cfg(feature = "image")]
+impl ImageRead for ::image::DynamicImage {
+    fn dims(&self) -> [usize; 2] {
+        [self.width() as usize, self.height() as usize]
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        use ::image::{GenericImageView, Pixel};
+        match self {
+            Self::ImageLuma8(image) => image.copy_line_as_spans_to(y, out),
+            _ => set_spans_by_fn(out, self.width() as usize, |x| {
+                self.get_pixel(x as u32, y as u32).to_luma().0[0] >= 128
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl LumaImageRead for ::image::DynamicImage {
+    fn dims(&self) -> [usize; 2] {
+        [self.width() as usize, self.height() as usize]
+    }
+
+    fn copy_line_as_luma_to(&self, y: usize, out: &mut [u8]) {
+        use ::image::{GenericImageView, Pixel};
+        for (x, out) in out.iter_mut().enumerate() {
+            *out = self.get_pixel(x as u32, y as u32).to_luma().0[0];
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl ColorImageRead for ::image::DynamicImage {
+    fn dims(&self) -> [usize; 2] {
+        [self.width() as usize, self.height() as usize]
+    }
+
+    fn copy_line_as_rgb_to(&self, y: usize, out: &mut [Rgb]) {
+        use ::image::{GenericImageView, Pixel};
+        for (x, out) in out.iter_mut().enumerate() {
+            *out = self.get_pixel(x as u32, y as u32).to_rgb().0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(image: &impl ImageRead, y: usize) -> Vec<Span> {
+        let mut out = vec![0; (image.dims()[0] + SPAN_BITS - 1) / SPAN_BITS];
+        image.copy_line_as_spans_to(y, &mut out);
+        out
+    }
+
+    #[test]
+    fn bitmap() {
+        // 20x2, 4 bytes per row
+        let data = [0b1000_0001, 0xff, 0b1111_0000, 0x00, 0, 0, 0b0001_0000, 0];
+        let image = BitmapImageRead::new(&data, [20, 2], 4, BitOrder::MsbFirst);
+        assert_eq!(spans(&image, 0), [0xff81, 0b1111]);
+        assert_eq!(spans(&image, 1), [0, 0b1000]);
+
+        let image = BitmapImageRead::new(&data, [20, 2], 4, BitOrder::LsbFirst);
+        assert_eq!(spans(&image, 0), [0xff81, 0]);
+    }
+
+    #[test]
+    fn gray() {
+        let data = [0, 127, 128, 255, 99, 99, 10, 200, 255, 0];
+        let mut image = GrayImageRead::with_stride(&data, [4, 2], 6);
+        assert_eq!(spans(&image, 0), [0b1100]);
+        assert_eq!(spans(&image, 1), [0b0110]);
+
+        image.invert = true;
+        assert_eq!(spans(&image, 0), [0b0011]);
+        let mut luma = [0; 4];
+        image.copy_line_as_luma_to(1, &mut luma);
+        assert_eq!(luma, [245, 55, 0, 255]);
+    }
+
+    #[test]
+    fn crop() {
+        // A 40x3 image with "on" pixels at x = 17..=35, y = 1
+        let data: Vec<u8> = (0..120)
+            .map(|i| {
+                if (i / 40 == 1) && (17..=35).contains(&(i % 40)) {
+                    255
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let image = GrayImageRead::new(&data, [40, 3]);
+        assert_eq!(content_bbox(&image, 0), Some([17..36, 1..2]));
+        assert_eq!(content_bbox(&image, 5), Some([12..40, 0..3]));
+        assert_eq!(content_bbox(&GrayImageRead::new(&[0; 4], [2, 2]), 1), None);
+
+        let cropped = CropImageRead::new(image, [40, 3], [15..38, 1..2]);
+        assert_eq!(ImageRead::dims(&cropped), [23, 1]);
+        assert_eq!(spans(&cropped, 0), [0xfffc, 0b001_1111]);
+        let mut luma = [0; 23];
+        cropped.copy_line_as_luma_to(0, &mut luma);
+        assert_eq!(luma[..3], [0, 0, 255]);
+    }
+
+    #[test]
+    fn rgba() {
+        let data = [255, 255, 255, 0, 255, 0, 0, 255, 0, 255, 0, 255];
+        let mut image = RgbaImageRead::new(&data, [3, 1]);
+        assert_eq!(spans(&image, 0), [0b101]);
+        image.luma_formula = LumaFormula::Average;
+        image.threshold = 85;
+        assert_eq!(spans(&image, 0), [0b111]);
+        let mut rgb = [[0; 3]; 3];
+        image.copy_line_as_rgb_to(0, &mut rgb);
+        assert_eq!(rgb, [[255; 3], [255, 0, 0], [0, 255, 0]]);
+    }
+}
@@ -91,3 +91,127 @@ pub fn median(histogram: &[u32]) -> usize {
     }
     unreachable!()
 }
+
+/// Thin the foreground (`true`) regions of a bitmap to 1-pixel-wide
+/// skeletons using the Zhang–Suen algorithm. `image` is a row-major bitmap of
+/// width `width`.
+pub fn skeletonize(image: &mut [bool], width: usize) {
+    if width == 0 {
+        return;
+    }
+    let height = image.len() / width;
+    let mut to_clear = Vec::new();
+
+    loop {
+        let mut changed = false;
+
+        for &step in &[0, 1] {
+            for y in 0..height {
+                for x in 0..width {
+                    if !image[x + y * width] {
+                        continue;
+                    }
+
+                    let get = |dx: isize, dy: isize| {
+                        let (x, y) = (x as isize + dx, y as isize + dy);
+                        x >= 0
+                            && y >= 0
+                            && (x as usize) < width
+                            && (y as usize) < height
+                            && image[x as usize + y as usize * width]
+                    };
+
+                    // P2, P3, ..., P9 (clockwise from the north)
+                    let p = [
+                        get(0, -1),
+                        get(1, -1),
+                        get(1, 0),
+                        get(1, 1),
+                        get(0, 1),
+                        get(-1, 1),
+                        get(-1, 0),
+                        get(-1, -1),
+                    ];
+                    let num_neighbors = p.iter().filter(|&&x| x).count();
+                    let num_transitions = (0..8).filter(|&i| !p[i] && p[(i + 1) % 8]).count();
+                    let [n, _, e, _, s, _, w, _] = p;
+                    let cond = if step == 0 {
+                        !(e && s && (n || w))
+                    } else {
+                        !(n && w && (e || s))
+                    };
+
+                    if (2..=6).contains(&num_neighbors) && num_transitions == 1 && cond {
+                        to_clear.push(x + y * width);
+                    }
+                }
+            }
+
+            changed |= !to_clear.is_empty();
+            for i in to_clear.drain(..) {
+                image[i] = false;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Binarize `image` at `threshold`, taking the minority as the foreground, and
+/// thin the foreground by [`skeletonize`]. The foreground becomes white (`255`)
+/// and the background black (`0`).
+pub fn binarize_and_skeletonize(image: &mut image::GrayImage, threshold: usize) {
+    let num_light = image
+        .iter()
+        .filter(|&&luma| luma as usize >= threshold)
+        .count();
+    // The minority is the foreground
+    let foreground_is_dark = num_light * 2 > image.len();
+    let mut bitmap: Vec<bool> = image
+        .iter()
+        .map(|&luma| (luma as usize >= threshold) ^ foreground_is_dark)
+        .collect();
+    skeletonize(&mut bitmap, image.width() as usize);
+    for (luma, &on) in image.iter_mut().zip(bitmap.iter()) {
+        *luma = if on { 255 } else { 0 };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skeletonize_bar() {
+        // A 3-pixel-thick horizontal bar becomes a 1-pixel-thick line (whose
+        // ends may be shortened)
+        let width = 9;
+        let mut image: Vec<bool> = (0..width * 5)
+            .map(|i| (1..4).contains(&(i / width)) && (1..8).contains(&(i % width)))
+            .collect();
+        skeletonize(&mut image, width);
+        for x in 3..6 {
+            let column: Vec<bool> = (0..5).map(|y| image[x + y * width]).collect();
+            assert_eq!(column, [false, false, true, false, false], "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn binarize_and_skeletonize_dark_bar() {
+        // The dark bar is the minority, so it becomes the white foreground
+        let mut image = image::GrayImage::from_fn(9, 5, |x, y| {
+            image::Luma([if (1..4).contains(&y) && (1..8).contains(&x) {
+                20
+            } else {
+                230
+            }])
+        });
+        binarize_and_skeletonize(&mut image, 128);
+        for x in 3..6 {
+            let column: Vec<u8> = (0..5).map(|y| image.get_pixel(x, y).0[0]).collect();
+            assert_eq!(column, [0, 0, 255, 0, 0], "x = {}", x);
+        }
+    }
+}
@@ -1,5 +1,5 @@
+use core::{convert, fmt, ops};
 use num_integer::Integer;
-use std::{convert, fmt, ops};
 
 /// Integral types with efficient binary operations.
 pub trait BinInteger:
@@ -76,7 +76,7 @@ macro_rules! impl_binary_integer {
         impl BinInteger for $type {
             type OneDigits = OneDigits<Self>;
 
-            const BITS: u32 = std::mem::size_of::<Self>() as u32 * 8;
+            const BITS: u32 = core::mem::size_of::<Self>() as u32 * 8;
 
             #[inline]
             fn ones(range: ops::Range<u32>) -> Self {
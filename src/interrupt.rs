@@ -0,0 +1,33 @@
+//! Ctrl-C handling
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(unix)]
+use std::os::raw::c_int;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch Ctrl-C (`SIGINT`) so that it's reported by [`is_interrupted`]
+/// instead of terminating the process. Returns `false` if this isn't supported
+/// on the current platform.
+#[cfg(unix)]
+pub fn install_handler() -> bool {
+    extern "C" fn handle_sigint(_: c_int) {
+        // Only async-signal-safe operations are allowed here
+        INTERRUPTED.store(true, Ordering::Relaxed);
+    }
+
+    let handler = handle_sigint as extern "C" fn(c_int) as libc::sighandler_t;
+    // Safety: `handle_sigint` is async-signal-safe
+    unsafe { libc::signal(libc::SIGINT, handler) != libc::SIG_ERR }
+}
+
+#[cfg(not(unix))]
+pub fn install_handler() -> bool {
+    false
+}
+
+/// Get a flag indicating whether Ctrl-C was pressed after
+/// [`install_handler`] was called.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
@@ -1,9 +1,45 @@
-use std::convert::TryInto;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::convert::TryInto;
+#[cfg(feature = "alloc")]
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+mod builder;
+// `SgrWriter` is only used by `Bmp2text`
+#[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+mod color;
+#[cfg(feature = "alloc")]
+mod diffusion;
+mod fidelity;
+#[cfg(feature = "alloc")]
+mod font;
+#[cfg(feature = "alloc")]
+mod glyphgrid;
+#[cfg(feature = "alloc")]
+mod glyphindex;
 mod glyphsets;
-mod image;
+pub mod image;
 mod int;
-pub use self::{glyphsets::*, image::*};
+#[cfg(feature = "alloc")]
+mod live;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "alloc")]
+mod postprocess;
+#[cfg(all(test, feature = "alloc"))]
+mod testutil;
+#[cfg(feature = "alloc")]
+mod text2bmp;
+#[cfg(feature = "parallel")]
+pub use self::parallel::*;
+#[cfg(feature = "alloc")]
+pub use self::{builder::*, font::*, glyphgrid::*, glyphindex::GlyphMetric, live::*, text2bmp::*};
+pub use self::{color::*, fidelity::*, glyphsets::*, image::*};
 
 /// A set of consecutive pixels of a constant length.
 ///
@@ -11,6 +47,7 @@ pub use self::{glyphsets::*, image::*};
 pub type Span = u16;
 
 /// The unsigned integer type twice as wide as `Span`.
+#[cfg(feature = "alloc")]
 type Span2 = u32;
 
 // FIXME: Waiting for `T::BITS` (https://github.com/rust-lang/rust/issues/76904)
@@ -24,6 +61,55 @@ pub type Fragment = u64;
 #[non_exhaustive]
 pub struct Bmp2textOpts<'a> {
     pub glyph_set: &'a dyn GlyphSet,
+    /// Emit SGR escape sequences to color each cell. Only honored by
+    /// [`Bmp2text::transform_and_write_colored`].
+    pub color: Option<ColorOpts>,
+    /// Diffuse the mismatch between each cell's input and its glyph's mask
+    /// into the neighboring cells not processed yet (glyph-level
+    /// Floyd–Steinberg dithering). This preserves shades with glyph sets that
+    /// can represent few patterns exactly, such as [`GLYPH_SET_SLC`].
+    /// Defaults to `false`.
+    ///
+    /// Not honored by [`Bmp2text::transform_luma_and_write`]. Makes
+    /// `par_transform_lines` fall back to sequential conversion.
+    pub error_diffusion: bool,
+    /// The line terminator. Defaults to [`LineTerminator::Lf`].
+    pub line_terminator: LineTerminator,
+    /// Remove whitespace and blank glyphs (the glyph for an empty fragment)
+    /// at the end of each line. Defaults to `false`.
+    ///
+    /// Not honored by [`Bmp2text::transform_and_write_colored`] if
+    /// [`ColorOpts::background`] is set.
+    pub trim_trailing_whitespace: bool,
+    /// Omit the lines consisting only of whitespace and blank glyphs at the
+    /// top and bottom of the output. Defaults to `false`.
+    ///
+    /// Blank lines are held back until a non-blank line follows them, so
+    /// `transform_lines` and its friends may buffer more than one line.
+    /// Not honored by [`Bmp2text::transform_and_write_colored`] if
+    /// [`ColorOpts::background`] is set.
+    pub strip_blank_rows: bool,
+    /// A string inserted at the start of each line (e.g., `"// "`). Defaults
+    /// to `""`.
+    pub line_prefix: &'a str,
+}
+
+/// Specifies the line terminator of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineTerminator {
+    /// `"\n"`
+    Lf,
+    /// `"\r\n"`
+    CrLf,
+}
+
+impl LineTerminator {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
 }
 
 impl Default for Bmp2textOpts<'_> {
@@ -36,16 +122,28 @@ impl Bmp2textOpts<'_> {
     pub fn new() -> Self {
         Self {
             glyph_set: GLYPH_SET_SLC,
+            color: None,
+            error_diffusion: false,
+            line_terminator: LineTerminator::Lf,
+            trim_trailing_whitespace: false,
+            strip_blank_rows: false,
+            line_prefix: "",
         }
     }
 }
 
 /// The working area for bitmap-to-text conversion.
+#[cfg(feature = "alloc")]
 #[derive(Default, Debug)]
 pub struct Bmp2text {
     row_group: Vec<Span>,
+    fragments: Vec<Fragment>,
+    rgb_row_group: Vec<Rgb>,
+    luma_row_group: Vec<u8>,
+    diffuser: diffusion::ErrorDiffuser,
 }
 
+#[cfg(feature = "alloc")]
 impl Bmp2text {
     pub fn new() -> Self {
         Self::default()
@@ -55,85 +153,604 @@ impl Bmp2text {
         &mut self,
         image: &impl ImageRead,
         opts: &Bmp2textOpts,
-        out: &mut impl std::fmt::Write,
-    ) -> std::fmt::Result {
-        use int::BinInteger;
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        self.transform_lines(image, opts, |line| out.write_str(line))
+    }
+
+    /// Like [`Self::transform_and_write`], but calls `f` with each output line
+    /// (including the line terminator) as soon as it's ready. Stops and returns
+    /// the error if `f` returns one.
+    ///
+    /// Unlike the output of [`Self::transform_and_write`], no more than one
+    /// line is buffered at a time.
+    pub fn transform_lines<E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts,
+        mut f: impl FnMut(&str) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let out_h = num_lines_for_image_height(image.dims()[1], opts);
+        let mut filter = postprocess::LineFilter::new(opts);
+        self.transform_line_range(image, opts, 0..out_h, |line| {
+            filter.push(line, &mut (), &mut |line, _| f(line))
+        })
+    }
+
+    /// Like [`Self::transform_lines`], but also passes the reconstruction error
+    /// of each cell (see [`cell_error`]) to `f` and accumulates them into
+    /// `stats`.
+    pub fn transform_lines_with_errors<E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts,
+        stats: &mut ErrorStats,
+        mut f: impl FnMut(&str, &[CellError]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let glyph_set = opts.glyph_set;
+        let mask_dims = glyph_set.mask_dims();
+
+        let [out_w, out_h] = [
+            num_glyphs_for_image_width(image.dims()[0], opts),
+            num_lines_for_image_height(image.dims()[1], opts),
+        ];
+
+        let mut line = String::with_capacity(out_w * glyph_set.max_glyph_len());
+        let mut adjusted_fragments = Vec::with_capacity(out_w);
+        let mut errors = Vec::with_capacity(out_w);
+        let mut filter = postprocess::LineFilter::new(opts);
+
+        self.diffuser.reset();
+
+        for out_y in 0..out_h {
+            read_fragment_line(
+                &mut self.row_group,
+                image,
+                glyph_set,
+                out_y,
+                out_w,
+                &mut self.fragments,
+            );
+
+            // The errors are measured against the original input even if
+            // error diffusion is enabled
+            adjusted_fragments.clear();
+            adjusted_fragments.extend_from_slice(&self.fragments);
+            if opts.error_diffusion {
+                self.diffuser
+                    .diffuse_line(glyph_set, &mut adjusted_fragments);
+            }
+
+            line.clear();
+            errors.clear();
+            for (&fragment, &adjusted) in self.fragments.iter().zip(adjusted_fragments.iter()) {
+                let glyph = glyph_set.fragment_to_glyph(adjusted);
+                line.push_str(glyph);
+
+                let error = glyph_error(glyph_set, glyph, fragment);
+                stats.add_cell(error, mask_dims[0] * mask_dims[1]);
+                errors.push(error);
+            }
+            filter.push(&line, &mut errors, &mut |line, errors| f(line, errors))?;
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Self::transform_lines`], but only produces the output lines in
+    /// `out_ys`. The lines are passed to `f` without post-processing (see
+    /// [`postprocess::LineFilter`]) or line terminators.
+    pub(crate) fn transform_line_range<E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts,
+        out_ys: core::ops::Range<usize>,
+        mut f: impl FnMut(&str) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let out_w = num_glyphs_for_image_width(image.dims()[0], opts);
+
+        let mut line = String::with_capacity(out_w * opts.glyph_set.max_glyph_len());
+
+        self.transform_grid_row_range(image, opts, out_ys, |cells| {
+            line.clear();
+            for cell in cells {
+                line.push_str(cell.glyph);
+            }
+            f(&line)
+        })
+    }
+
+    /// Convert `image` to a [`GlyphGrid`], which describes each cell in a
+    /// structured form for custom renderers.
+    pub fn transform_to_grid<'a>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts<'a>,
+    ) -> GlyphGrid<'a> {
+        let dims = [
+            num_glyphs_for_image_width(image.dims()[0], opts),
+            num_lines_for_image_height(image.dims()[1], opts),
+        ];
+        let mut cells = Vec::with_capacity(dims[0] * dims[1]);
+        self.transform_grid_rows(image, opts, |row| {
+            cells.extend_from_slice(row);
+            Ok::<_, core::convert::Infallible>(())
+        })
+        .unwrap();
+        GlyphGrid::new(dims, cells)
+    }
+
+    /// Like [`Self::transform_to_grid`], but calls `f` with each row of cells
+    /// as soon as it's ready. Stops and returns the error if `f` returns one.
+    pub fn transform_grid_rows<'a, E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts<'a>,
+        f: impl FnMut(&[GlyphCell<'a>]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let out_h = num_lines_for_image_height(image.dims()[1], opts);
+        self.transform_grid_row_range(image, opts, 0..out_h, f)
+    }
+
+    /// Like [`Self::transform_grid_rows`], but only produces the rows in
+    /// `out_ys`.
+    fn transform_grid_row_range<'a, E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts<'a>,
+        out_ys: core::ops::Range<usize>,
+        mut f: impl FnMut(&[GlyphCell<'a>]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let glyph_set = opts.glyph_set;
+        let mask_dims = glyph_set.mask_dims();
+        let mask_overlap = glyph_set.mask_overlap();
+        let stride = [
+            mask_dims[0] - mask_overlap[0],
+            mask_dims[1] - mask_overlap[1],
+        ];
+
+        let out_w = num_glyphs_for_image_width(image.dims()[0], opts);
+
+        let mut cells = Vec::with_capacity(out_w);
+        let mut adjusted_fragments = Vec::new();
+
+        self.diffuser.reset();
+
+        for out_y in out_ys {
+            read_fragment_line(
+                &mut self.row_group,
+                image,
+                glyph_set,
+                out_y,
+                out_w,
+                &mut self.fragments,
+            );
+
+            // Keep the original fragments for `GlyphCell::fragment`
+            let chosen_fragments = if opts.error_diffusion {
+                adjusted_fragments.clear();
+                adjusted_fragments.extend_from_slice(&self.fragments);
+                self.diffuser
+                    .diffuse_line(glyph_set, &mut adjusted_fragments);
+                &adjusted_fragments
+            } else {
+                &self.fragments
+            };
+
+            cells.clear();
+            let y = out_y * stride[1];
+            for (out_x, (&fragment, &chosen)) in self
+                .fragments
+                .iter()
+                .zip(chosen_fragments.iter())
+                .enumerate()
+            {
+                let x = out_x * stride[0];
+                cells.push(GlyphCell {
+                    glyph_index: glyph_set.fragment_to_glyph_index(chosen),
+                    glyph: glyph_set.fragment_to_glyph(chosen),
+                    fragment,
+                    rect: [x..x + mask_dims[0], y..y + mask_dims[1]],
+                });
+            }
+            f(&cells)?;
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Self::transform_to_grid`], but only converts the cells in
+    /// `cells` (`[x, y]`, measured in cells). The result is identical to the
+    /// same region of the output of [`Self::transform_to_grid`], so adjacent
+    /// viewports can be put together to reproduce the whole output.
+    ///
+    /// If [`Bmp2textOpts::error_diffusion`] is enabled, the cells above and to
+    /// the left of the viewport have to be converted as well because the
+    /// viewport depends on them.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `cells` is not within the output dimensions.
+    pub fn transform_viewport_to_grid<'a>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts<'a>,
+        cells: [core::ops::Range<usize>; 2],
+    ) -> GlyphGrid<'a> {
+        let dims = [cells[0].len(), cells[1].len()];
+        let mut out_cells = Vec::with_capacity(dims[0] * dims[1]);
+        self.transform_viewport_grid_rows(image, opts, cells, |row| {
+            out_cells.extend_from_slice(row);
+            Ok::<_, core::convert::Infallible>(())
+        })
+        .unwrap();
+        GlyphGrid::new(dims, out_cells)
+    }
+
+    /// Like [`Self::transform_viewport_to_grid`], but calls `f` with each
+    /// output line of the viewport (without a line terminator). The output
+    /// options such as [`Bmp2textOpts::line_prefix`] are not applied.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `cells` is not within the output dimensions.
+    pub fn transform_viewport_lines<E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts,
+        cells: [core::ops::Range<usize>; 2],
+        mut f: impl FnMut(&str) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut line = String::with_capacity(cells[0].len() * opts.glyph_set.max_glyph_len());
+        self.transform_viewport_grid_rows(image, opts, cells, |row| {
+            line.clear();
+            for cell in row {
+                line.push_str(cell.glyph);
+            }
+            f(&line)
+        })
+    }
+
+    /// Like [`Self::transform_grid_rows`], but only produces the cells in
+    /// `cells`. See [`Self::transform_viewport_to_grid`].
+    pub fn transform_viewport_grid_rows<'a, E>(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts<'a>,
+        cells: [core::ops::Range<usize>; 2],
+        mut f: impl FnMut(&[GlyphCell<'a>]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let glyph_set = opts.glyph_set;
+        let mask_dims = glyph_set.mask_dims();
+        let mask_overlap = glyph_set.mask_overlap();
+        let stride = [
+            mask_dims[0] - mask_overlap[0],
+            mask_dims[1] - mask_overlap[1],
+        ];
+
+        let [xs, ys] = cells;
+        let out_dims = [
+            num_glyphs_for_image_width(image.dims()[0], opts),
+            num_lines_for_image_height(image.dims()[1], opts),
+        ];
+        assert!(
+            xs.start <= xs.end && xs.end <= out_dims[0],
+            "viewport is out of bounds"
+        );
+        assert!(
+            ys.start <= ys.end && ys.end <= out_dims[1],
+            "viewport is out of bounds"
+        );
+
+        if xs.is_empty() || ys.is_empty() {
+            for _ in ys {
+                f(&[])?;
+            }
+            return Ok(());
+        }
+
+        if opts.error_diffusion {
+            // Convert everything up to the viewport's bottom and discard the
+            // cells outside the viewport
+            let mut out_y = 0;
+            return self.transform_grid_row_range(image, opts, 0..ys.end, |row| {
+                out_y += 1;
+                if out_y > ys.start {
+                    f(&row[xs.clone()])
+                } else {
+                    Ok(())
+                }
+            });
+        }
+
+        // The cells in the viewport only depend on the pixels they cover.
+        // Converting that region by itself produces the same cells.
+        let rect = [
+            xs.start * stride[0]..xs.end * stride[0] + mask_overlap[0],
+            ys.start * stride[1]..ys.end * stride[1] + mask_overlap[1],
+        ];
+        let offset = [rect[0].start, rect[1].start];
+        let cropped = CropImageRead::new(image, image.dims(), rect);
+
+        let mut shifted_cells = Vec::with_capacity(xs.len());
+        self.transform_grid_row_range(&cropped, opts, 0..ys.len(), |row| {
+            shifted_cells.clear();
+            shifted_cells.extend(row.iter().map(|cell| {
+                let [cell_xs, cell_ys] = &cell.rect;
+                GlyphCell {
+                    rect: [
+                        cell_xs.start + offset[0]..cell_xs.end + offset[0],
+                        cell_ys.start + offset[1]..cell_ys.end + offset[1],
+                    ],
+                    ..cell.clone()
+                }
+            }));
+            f(&shifted_cells)
+        })
+    }
+
+    /// Like [`Self::transform_and_write`], but also colors each cell based on
+    /// `color_image` as specified by [`Bmp2textOpts::color`].
+    ///
+    /// The foreground color of a cell is the average color of the pixels set
+    /// in the cell's input fragment. The background color is the average color
+    /// of the rest.
+    ///
+    /// `color_image` must have the same dimensions as `image`.
+    pub fn transform_and_write_colored(
+        &mut self,
+        image: &impl ImageRead,
+        color_image: &impl ColorImageRead,
+        opts: &Bmp2textOpts,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        self.transform_lines_colored(image, color_image, opts, |line| out.write_str(line))
+    }
+
+    /// The line-by-line version of [`Self::transform_and_write_colored`]. See
+    /// [`Self::transform_lines`].
+    pub fn transform_lines_colored<E>(
+        &mut self,
+        image: &impl ImageRead,
+        color_image: &impl ColorImageRead,
+        opts: &Bmp2textOpts,
+        mut f: impl FnMut(&str) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let color_opts = if let Some(x) = opts.color {
+            x
+        } else {
+            return self.transform_lines(image, opts, f);
+        };
 
         let glyph_set = opts.glyph_set;
         let mask_dims = glyph_set.mask_dims();
         let mask_overlap = glyph_set.mask_overlap();
 
         let [img_w, img_h] = image.dims();
-        let num_spans_per_line = (img_w + SPAN_BITS - 1) / SPAN_BITS;
+        assert_eq!(color_image.dims(), [img_w, img_h]);
         let [out_w, out_h] = [
             num_glyphs_for_image_width(img_w, opts),
             num_lines_for_image_height(img_h, opts),
         ];
 
-        let num_spans_per_line_extra = num_spans_per_line + 1;
-        self.row_group
-            .resize(num_spans_per_line_extra * mask_dims[1], 0);
-        let mut row_group: Vec<&mut [Span]> = self
-            .row_group
-            .chunks_exact_mut(num_spans_per_line_extra)
-            .collect();
-        let row_group: &mut [&mut [Span]] = &mut row_group[..mask_dims[1]];
+        self.rgb_row_group.resize(img_w * mask_dims[1], [0; 3]);
 
-        // The scanning state of each row in `row_group`
-        #[derive(Clone, Copy)]
-        struct RowState {
-            bits: Span2,
+        let mut sgr = color::SgrWriter::default();
+        let mut line = String::with_capacity(
+            out_w * (glyph_set.max_glyph_len() + color_opts.max_escape_len_per_glyph())
+                + color_opts.max_escape_len_per_line(),
+        );
+        // Trailing and blank cells are significant if they have background
+        // colors. Otherwise, they can be trimmed along with the escape
+        // sequences preceding them.
+        let mut filter = postprocess::LineFilter::new(opts);
+        if color_opts.background {
+            filter = filter.without_whitespace_processing();
         }
-        let mut row_states = [RowState { bits: 0 }; 16];
-        let row_states = &mut row_states[0..mask_dims[1]];
+
+        self.diffuser.reset();
 
         for out_y in 0..out_h {
-            // Read a row group from the input image
-            for (y, row) in row_group.iter_mut().enumerate() {
-                image.copy_line_as_spans_to(out_y * (mask_dims[1] - mask_overlap[1]) + y, row);
+            read_fragment_line(
+                &mut self.row_group,
+                image,
+                glyph_set,
+                out_y,
+                out_w,
+                &mut self.fragments,
+            );
+            if opts.error_diffusion {
+                self.diffuser.diffuse_line(glyph_set, &mut self.fragments);
             }
 
-            let mut num_valid_bits = 0; // .. in `RowState::bits`
-            let mut span_i = 0;
+            let y0 = out_y * (mask_dims[1] - mask_overlap[1]);
+            for y in 0..mask_dims[1] {
+                color_image
+                    .copy_line_as_rgb_to(y0 + y, &mut self.rgb_row_group[y * img_w..][..img_w]);
+            }
 
-            for _ in 0..out_w {
-                if num_valid_bits < mask_dims[0] {
-                    for (row_state, row) in row_states.iter_mut().zip(row_group.iter_mut()) {
-                        row_state.bits |= (row[span_i] as Span2) << num_valid_bits;
-                    }
-                    span_i += 1;
-                    num_valid_bits += SPAN_BITS;
+            line.clear();
+            // The end of the last non-blank glyph and the SGR state there
+            let mut visible_end = None;
+            for (out_x, &fragment) in self.fragments.iter().enumerate() {
+                let [fg, bg] = color::average_cell_colors(
+                    &self.rgb_row_group,
+                    img_w,
+                    out_x * (mask_dims[0] - mask_overlap[0]),
+                    mask_dims,
+                    fragment,
+                );
+                let bg = bg.filter(|_| color_opts.background);
+                // Writing to `String` is infallible
+                sgr.set(fg, bg, color_opts.mode, &mut line).unwrap();
+
+                // Find the glyph
+                let glyph = glyph_set.fragment_to_glyph(fragment);
+                line.push_str(glyph);
+                if !filter.is_blank_glyph(glyph) {
+                    visible_end = Some((line.len(), sgr.clone()));
                 }
+            }
+            if opts.trim_trailing_whitespace && !color_opts.background {
+                let (len, sgr_there) = visible_end.clone().unwrap_or_default();
+                line.truncate(len);
+                sgr = sgr_there;
+            }
+            sgr.reset(&mut line).unwrap();
+            filter.push_trimmed(&line, visible_end.is_none(), &mut (), &mut |line, _| {
+                f(line)
+            })?;
+        }
 
-                // Collect an input fragment of dimensions `mask_dims`
-                let mut fragment: Fragment = 0;
-                for (i, row_state) in row_states.iter_mut().enumerate() {
-                    fragment |= (row_state.bits as Fragment & Fragment::ones(0..mask_dims[0] as _))
-                        << (i * mask_dims[0]);
+        Ok(())
+    }
 
-                    row_state.bits >>= mask_dims[0] - mask_overlap[0];
-                }
-                num_valid_bits -= mask_dims[0] - mask_overlap[0];
+    /// Like [`Self::transform_and_write`], but takes a grayscale image and
+    /// chooses glyphs by [`GlyphSet::luma_cell_to_glyph`]. This is the natural
+    /// way to use intensity-based glyph sets such as [`GLYPH_SET_RAMP`].
+    pub fn transform_luma_and_write(
+        &mut self,
+        image: &impl LumaImageRead,
+        opts: &Bmp2textOpts,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        self.transform_luma_lines(image, opts, |line| out.write_str(line))
+    }
+
+    /// The line-by-line version of [`Self::transform_luma_and_write`]. See
+    /// [`Self::transform_lines`].
+    pub fn transform_luma_lines<E>(
+        &mut self,
+        image: &impl LumaImageRead,
+        opts: &Bmp2textOpts,
+        mut f: impl FnMut(&str) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let glyph_set = opts.glyph_set;
+        let mask_dims = glyph_set.mask_dims();
+        let mask_overlap = glyph_set.mask_overlap();
+
+        let [img_w, img_h] = image.dims();
+        let [out_w, out_h] = [
+            num_glyphs_for_image_width(img_w, opts),
+            num_lines_for_image_height(img_h, opts),
+        ];
+
+        self.luma_row_group.resize(img_w * mask_dims[1], 0);
+
+        let mut cell = [0u8; 64];
+        let cell = &mut cell[..mask_dims[0] * mask_dims[1]];
+        let mut line = String::with_capacity(out_w * glyph_set.max_glyph_len());
+        let mut filter = postprocess::LineFilter::new(opts);
+
+        for out_y in 0..out_h {
+            // Read a row group from the input image
+            let y0 = out_y * (mask_dims[1] - mask_overlap[1]);
+            for y in 0..mask_dims[1] {
+                image.copy_line_as_luma_to(y0 + y, &mut self.luma_row_group[y * img_w..][..img_w]);
+            }
 
-                debug_assert!(fragment < (1 << (mask_dims[0] * mask_dims[1])));
+            line.clear();
+            for out_x in 0..out_w {
+                // Collect an input cell of dimensions `mask_dims`
+                let x0 = out_x * (mask_dims[0] - mask_overlap[0]);
+                for (y, cell_row) in cell.chunks_exact_mut(mask_dims[0]).enumerate() {
+                    cell_row
+                        .copy_from_slice(&self.luma_row_group[y * img_w + x0..][..mask_dims[0]]);
+                }
 
                 // Find the glyph
-                let glyph = glyph_set.fragment_to_glyph(fragment);
-                out.write_str(glyph)?;
+                let glyph = glyph_set.luma_cell_to_glyph(cell);
+                line.push_str(glyph);
             }
-            out.write_str("\n")?;
+            filter.push(&line, &mut (), &mut |line, _| f(line))?;
         }
 
         Ok(())
     }
 }
 
+/// Read the row group for the output line `out_y` from `image` and convert it
+/// to `out_w` input fragments, which are stored in `fragments`.
+#[cfg(feature = "alloc")]
+fn read_fragment_line(
+    row_group: &mut Vec<Span>,
+    image: &impl ImageRead,
+    glyph_set: &dyn GlyphSet,
+    out_y: usize,
+    out_w: usize,
+    fragments: &mut Vec<Fragment>,
+) {
+    use int::BinInteger;
+
+    let mask_dims = glyph_set.mask_dims();
+    let mask_overlap = glyph_set.mask_overlap();
+
+    let [img_w, _] = image.dims();
+    let num_spans_per_line = (img_w + SPAN_BITS - 1) / SPAN_BITS;
+
+    let num_spans_per_line_extra = num_spans_per_line + 1;
+    row_group.resize(num_spans_per_line_extra * mask_dims[1], 0);
+
+    // Read a row group from the input image
+    for (y, row) in row_group
+        .chunks_exact_mut(num_spans_per_line_extra)
+        .enumerate()
+    {
+        image.copy_line_as_spans_to(out_y * (mask_dims[1] - mask_overlap[1]) + y, row);
+    }
+
+    // The scanning state of each row in `row_group`
+    #[derive(Clone, Copy)]
+    struct RowState {
+        bits: Span2,
+    }
+    let mut row_states = [RowState { bits: 0 }; 16];
+    let row_states = &mut row_states[0..mask_dims[1]];
+
+    let mut num_valid_bits = 0; // .. in `RowState::bits`
+    let mut span_i = 0;
+
+    fragments.clear();
+    for _ in 0..out_w {
+        if num_valid_bits < mask_dims[0] {
+            for (row_state, row) in row_states
+                .iter_mut()
+                .zip(row_group.chunks_exact(num_spans_per_line_extra))
+            {
+                row_state.bits |= (row[span_i] as Span2) << num_valid_bits;
+            }
+            span_i += 1;
+            num_valid_bits += SPAN_BITS;
+        }
+
+        // Collect an input fragment of dimensions `mask_dims`
+        let mut fragment: Fragment = 0;
+        for (i, row_state) in row_states.iter_mut().enumerate() {
+            fragment |= (row_state.bits as Fragment & Fragment::ones(0..mask_dims[0] as _))
+                << (i * mask_dims[0]);
+
+            row_state.bits >>= mask_dims[0] - mask_overlap[0];
+        }
+        num_valid_bits -= mask_dims[0] - mask_overlap[0];
+
+        debug_assert!(fragment < (1 << (mask_dims[0] * mask_dims[1])));
+
+        fragments.push(fragment);
+    }
+}
+
 pub fn num_glyphs_for_image_width(width: usize, opts: &Bmp2textOpts) -> usize {
     let mask_dims = opts.glyph_set.mask_dims();
     let mask_overlap = opts.glyph_set.mask_overlap();
     width.saturating_sub(mask_overlap[0]) / (mask_dims[0] - mask_overlap[0])
 }
 
+/// Like [`num_glyphs_for_image_width`], but measures the output width in
+/// display columns (see [`GlyphSet::glyph_width`]).
+pub fn num_columns_for_image_width(width: usize, opts: &Bmp2textOpts) -> usize {
+    num_glyphs_for_image_width(width, opts) * opts.glyph_set.glyph_width()
+}
+
 pub fn num_lines_for_image_height(height: usize, opts: &Bmp2textOpts) -> usize {
     let mask_dims = opts.glyph_set.mask_dims();
     let mask_overlap = opts.glyph_set.mask_overlap();
@@ -141,18 +758,33 @@ pub fn num_lines_for_image_height(height: usize, opts: &Bmp2textOpts) -> usize {
 }
 
 /// Calculate the maximum number of bytes possibly outputted by
-/// [`Bmp2text::transform_and_write`].
+/// [`Bmp2text::transform_and_write`] or
+/// [`Bmp2text::transform_and_write_colored`].
+///
+/// This counts glyphs, not display columns; a double-width glyph is accounted
+/// for by [`GlyphSet::max_glyph_len`].
 pub fn max_output_len_for_image_dims(
     [width, height]: [usize; 2],
     opts: &Bmp2textOpts,
 ) -> Option<usize> {
     let glyph_set = opts.glyph_set;
+    let line_term_len = opts.line_prefix.len() + opts.line_terminator.as_str().len();
+    let (max_glyph_len, max_line_term_len) = if let Some(color) = &opts.color {
+        (
+            glyph_set.max_glyph_len() + color.max_escape_len_per_glyph(),
+            line_term_len + color.max_escape_len_per_line(),
+        )
+    } else {
+        (glyph_set.max_glyph_len(), line_term_len)
+    };
     num_glyphs_for_image_width(width, opts)
-        .checked_mul(glyph_set.max_glyph_len())
-        .and_then(|x| x.checked_add(1)) // line termination
+        .checked_mul(max_glyph_len)
+        .and_then(|x| x.checked_add(max_line_term_len)) // line prefix and termination
         .and_then(|x| x.checked_mul(num_lines_for_image_height(height, opts)))
 }
 
+/// `output_dims` is measured in display columns and lines. `cell_width` is the
+/// width of a display column relative to its height.
 #[doc(hidden)]
 #[cfg(feature = "log")]
 pub fn adjust_image_size_for_output_size_preserving_aspect_ratio(
@@ -171,11 +803,14 @@ pub fn adjust_image_size_for_output_size_preserving_aspect_ratio(
         num_glyphs_for_image_width(image_dims[0], opts),
         num_lines_for_image_height(image_dims[1], opts),
     ];
+    let glyph_width = opts.glyph_set.glyph_width();
     let aspect = (mask_dims[1] - mask_overlap[1]) as f64 / (mask_dims[0] - mask_overlap[0]) as f64
-        * cell_width;
+        * cell_width
+        * glyph_width as f64;
 
+    // Measured in display columns and lines
     let [img_w, img_h] = [
-        nat_out_w as f64 / aspect.max(1.0),
+        (nat_out_w * glyph_width) as f64 / aspect.max(1.0),
         nat_out_h as f64 * aspect.min(1.0),
     ];
     log::debug!("'natural' output size = {:?}", [img_w, img_h]);
@@ -192,14 +827,17 @@ pub fn adjust_image_size_for_output_size_preserving_aspect_ratio(
     }
     log::debug!("scaling the 'natural' output size by {}...", scale);
 
+    // Round to nearest (`f64::round` is unavailable in `no_std`)
     let output_dims = [
-        (img_w * scale).round() as usize,
-        (img_h * scale).round() as usize,
+        (img_w * scale + 0.5) as usize,
+        (img_h * scale + 0.5) as usize,
     ];
 
     adjust_image_size_for_output_size(output_dims, opts)
 }
 
+/// `output_dims[0]` is measured in display columns. If it's not a multiple of
+/// [`GlyphSet::glyph_width`], it's rounded down.
 #[doc(hidden)]
 pub fn adjust_image_size_for_output_size(
     output_dims: [usize; 2],
@@ -209,7 +847,7 @@ pub fn adjust_image_size_for_output_size(
     let mask_overlap = opts.glyph_set.mask_overlap();
 
     Some([
-        output_dims[0]
+        (output_dims[0] / opts.glyph_set.glyph_width())
             .checked_mul(mask_dims[0] - mask_overlap[0])?
             .checked_add(mask_overlap[0])?
             .try_into()
@@ -221,3 +859,231 @@ pub fn adjust_image_size_for_output_size(
             .ok()?,
     ])
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::testutil::NoiseImage;
+
+    /// A test image given as rows of `#` (on) and `.` (off).
+    struct TextImage(&'static [&'static str]);
+
+    impl ImageRead for TextImage {
+        fn dims(&self) -> [usize; 2] {
+            [self.0[0].len(), self.0.len()]
+        }
+
+        fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+            let row = self.0[y].as_bytes();
+            set_spans_by_fn(out, row.len(), |x| row[x] == b'#');
+        }
+    }
+
+    const IMAGE: TextImage = TextImage(&[
+        "##..#.", //
+        "##..#.", //
+        "..##..", //
+        "..##.#", //
+    ]);
+
+    #[test]
+    fn transform_lines() {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_1X2;
+
+        let mut whole = String::new();
+        Bmp2text::new()
+            .transform_and_write(&IMAGE, &opts, &mut whole)
+            .unwrap();
+        assert_eq!(whole, "██  █ \n  ██ ▄\n");
+
+        let mut lines = Vec::new();
+        Bmp2text::new()
+            .transform_lines(&IMAGE, &opts, |line| {
+                lines.push(line.to_owned());
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert_eq!(lines, ["██  █ \n", "  ██ ▄\n"]);
+
+        // An error stops the conversion
+        let mut num_calls = 0;
+        let result = Bmp2text::new().transform_lines(&IMAGE, &opts, |_| {
+            num_calls += 1;
+            Err("stop")
+        });
+        assert_eq!(result, Err("stop"));
+        assert_eq!(num_calls, 1);
+    }
+
+    #[test]
+    fn transform_to_grid() {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_MS_2X3;
+
+        let grid = Bmp2text::new().transform_to_grid(&IMAGE, &opts);
+        assert_eq!(grid.dims(), [5, 1]);
+
+        let mut text = String::new();
+        Bmp2text::new()
+            .transform_and_write(&IMAGE, &opts, &mut text)
+            .unwrap();
+        assert_eq!(grid.to_string(), text);
+
+        // Neighboring cells share a column
+        let cell = grid.get([1, 0]);
+        assert_eq!(cell.rect, [1..3, 0..3]);
+        assert_eq!(cell.fragment, 0b10_01_01);
+        assert_eq!(
+            cell.glyph,
+            GLYPH_SET_MS_2X3.fragment_to_glyph(cell.fragment)
+        );
+        assert_eq!(
+            cell.glyph_index,
+            GLYPH_SET_MS_2X3.fragment_to_glyph_index(cell.fragment)
+        );
+        assert_eq!(grid.rows().count(), 1);
+    }
+
+    /// An image filled with a single color
+    struct SolidColorImage([usize; 2], Rgb);
+
+    impl ColorImageRead for SolidColorImage {
+        fn dims(&self) -> [usize; 2] {
+            self.0
+        }
+
+        fn copy_line_as_rgb_to(&self, _y: usize, out: &mut [Rgb]) {
+            out.fill(self.1);
+        }
+    }
+
+    #[test]
+    fn transform_colored_trim() {
+        let image = TextImage(&[
+            "......", //
+            "......", //
+            "#.#...", //
+            "#.#...", //
+            "......", //
+            "......", //
+        ]);
+        let color_image = SolidColorImage(image.dims(), [255, 0, 0]);
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_1X2;
+        opts.trim_trailing_whitespace = true;
+        opts.strip_blank_rows = true;
+        let mut color_opts = ColorOpts::new(ColorMode::Truecolor);
+        opts.color = Some(color_opts);
+
+        let mut text = String::new();
+        Bmp2text::new()
+            .transform_and_write_colored(&image, &color_image, &opts, &mut text)
+            .unwrap();
+        assert_eq!(text, "\x1b[38;2;255;0;0m█ █\x1b[0m\n");
+
+        // Blank cells are significant if they have background colors
+        color_opts.background = true;
+        opts.color = Some(color_opts);
+        let mut text = String::new();
+        Bmp2text::new()
+            .transform_and_write_colored(&image, &color_image, &opts, &mut text)
+            .unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.lines().all(|line| line.ends_with("   \x1b[0m")));
+    }
+
+    #[test]
+    fn viewport() {
+        let image = NoiseImage([101, 67], 2);
+        for &glyph_set in &[GLYPH_SET_MS_2X3, GLYPH_SET_SLC, GLYPH_SET_BRAILLE8] {
+            for &error_diffusion in &[false, true] {
+                let mut opts = Bmp2textOpts::new();
+                opts.glyph_set = glyph_set;
+                opts.error_diffusion = error_diffusion;
+
+                let mut b2t = Bmp2text::new();
+                let full = b2t.transform_to_grid(&image, &opts);
+                let [w, h] = full.dims();
+
+                // Tiles of uneven sizes, including empty ones
+                let xs = [0, 0, 7, w / 2, w - 1, w];
+                let ys = [0, 3, h / 3, h / 3, h];
+                let mut lines = vec![String::new(); h];
+                for y in ys.windows(2) {
+                    for x in xs.windows(2) {
+                        let tile =
+                            b2t.transform_viewport_to_grid(&image, &opts, [x[0]..x[1], y[0]..y[1]]);
+                        assert_eq!(tile.dims(), [x[1] - x[0], y[1] - y[0]]);
+                        for (row, out_y) in tile.rows().zip(y[0]..) {
+                            assert_eq!(row, &full.row(out_y)[x[0]..x[1]]);
+                        }
+
+                        let mut out_y = y[0];
+                        b2t.transform_viewport_lines(
+                            &image,
+                            &opts,
+                            [x[0]..x[1], y[0]..y[1]],
+                            |line| {
+                                lines[out_y].push_str(line);
+                                out_y += 1;
+                                Ok::<_, ()>(())
+                            },
+                        )
+                        .unwrap();
+                    }
+                }
+
+                let lines: String = lines.iter().map(|line| line.clone() + "\n").collect();
+                assert_eq!(lines, full.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn wide_glyphs() {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_1X1_WIDE;
+
+        let mut text = String::new();
+        Bmp2text::new()
+            .transform_and_write(&IMAGE, &opts, &mut text)
+            .unwrap();
+        assert!(text.starts_with("████    ██  \n"));
+        assert!(text.len() <= max_output_len_for_image_dims(IMAGE.dims(), &opts).unwrap());
+
+        assert_eq!(num_glyphs_for_image_width(6, &opts), 6);
+        assert_eq!(num_columns_for_image_width(6, &opts), 12);
+        // 80 columns fit 40 glyphs
+        assert_eq!(
+            adjust_image_size_for_output_size([80, 40], &opts),
+            Some([40, 40])
+        );
+        assert_eq!(
+            adjust_image_size_for_output_size([81, 40], &opts),
+            Some([40, 40])
+        );
+    }
+
+    #[test]
+    fn transform_lines_with_errors() {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_1X2;
+
+        let mut stats = ErrorStats::new();
+        let mut lines = Vec::new();
+        Bmp2text::new()
+            .transform_lines_with_errors(&IMAGE, &opts, &mut stats, |line, errors| {
+                assert_eq!(line.chars().count(), errors.len() + 1);
+                lines.push(line.to_owned());
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert_eq!(lines, ["██  █ \n", "  ██ ▄\n"]);
+
+        // `GLYPH_SET_1X2` can represent every fragment exactly
+        assert_eq!(stats.num_cells, 12);
+        assert_eq!(stats.num_exact_cells, 12);
+        assert_eq!(stats.fidelity(), Some(1.0));
+    }
+}
@@ -0,0 +1,337 @@
+//! Incremental rendering for live displays
+use alloc::{string::String, vec::Vec};
+use core::{convert::Infallible, fmt, mem::swap};
+
+use crate::{
+    num_glyphs_for_image_width, num_lines_for_image_height, Bmp2text, Bmp2textOpts, ImageRead,
+};
+
+/// Renders successive frames to a terminal, only emitting the glyphs that
+/// changed since the previous frame along with the cursor movements to reach
+/// them.
+///
+/// The first frame is written at the cursor position like
+/// [`Bmp2text::transform_and_write`] does, and the cursor is left at the start
+/// of the line below the frame after each frame. The following frames are
+/// drawn over the previous one by relative cursor movements, so nothing else
+/// may be written to the terminal in between. If the output dimensions change,
+/// the previous frame is erased and the new one is drawn in full.
+///
+/// Only [`Bmp2textOpts::glyph_set`] and [`Bmp2textOpts::error_diffusion`] are
+/// honored.
+#[derive(Debug, Default)]
+pub struct LiveRenderer {
+    b2t: Bmp2text,
+    prev: Frame,
+    cur: Frame,
+    /// `prev` is on the screen
+    has_prev: bool,
+}
+
+/// The glyphs of a frame
+#[derive(Debug, Default)]
+struct Frame {
+    /// The dimensions, measured in cells
+    dims: [usize; 2],
+    glyph_width: usize,
+    /// The glyphs in row-major order
+    text: String,
+    /// The end offset of each glyph in `text`
+    ends: Vec<usize>,
+}
+
+impl Frame {
+    fn glyph(&self, i: usize) -> &str {
+        let start = if i == 0 { 0 } else { self.ends[i - 1] };
+        &self.text[start..self.ends[i]]
+    }
+}
+
+/// The cursor position relative to the frame's upper left corner
+struct Cursor {
+    y: usize,
+    /// The column, measured in display columns. `None` if unknown, which
+    /// happens after writing the last column of the frame because the
+    /// terminal may defer the line wrap.
+    x: Option<usize>,
+}
+
+impl LiveRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the previous frame so that the next frame is drawn in full at
+    /// the cursor position. Call this if the screen was modified by someone
+    /// else.
+    pub fn reset(&mut self) {
+        self.has_prev = false;
+    }
+
+    /// Convert `image` and write the escape sequences and glyphs to update
+    /// the previous frame to `out`.
+    pub fn render(
+        &mut self,
+        image: &impl ImageRead,
+        opts: &Bmp2textOpts,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        let cur = &mut self.cur;
+        cur.dims = [
+            num_glyphs_for_image_width(image.dims()[0], opts),
+            num_lines_for_image_height(image.dims()[1], opts),
+        ];
+        cur.glyph_width = opts.glyph_set.glyph_width();
+        cur.text.clear();
+        cur.ends.clear();
+        self.b2t
+            .transform_grid_rows(image, opts, |row| {
+                for cell in row {
+                    cur.text.push_str(cell.glyph);
+                    cur.ends.push(cur.text.len());
+                }
+                Ok::<_, Infallible>(())
+            })
+            .unwrap();
+
+        let result = if self.has_prev
+            && self.prev.dims == self.cur.dims
+            && self.prev.glyph_width == self.cur.glyph_width
+        {
+            self.write_diff(out)
+        } else {
+            self.write_full(out)
+        };
+
+        swap(&mut self.prev, &mut self.cur);
+        // The screen contents are unknown if the write failed
+        self.has_prev = result.is_ok();
+        result
+    }
+
+    /// Erase the previous frame (if any) and write `self.cur`.
+    fn write_full(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        if self.has_prev {
+            out.write_char('\r')?;
+            if self.prev.dims[1] > 0 {
+                write!(out, "\x1b[{}A", self.prev.dims[1])?;
+            }
+            // Erase below
+            out.write_str("\x1b[J")?;
+        }
+
+        let [w, h] = self.cur.dims;
+        for y in 0..h {
+            for x in 0..w {
+                out.write_str(self.cur.glyph(x + y * w))?;
+            }
+            out.write_str("\r\n")?;
+        }
+        Ok(())
+    }
+
+    /// Update `self.prev` on the screen to `self.cur`. They must have the same
+    /// dimensions.
+    fn write_diff(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        let [w, h] = self.cur.dims;
+        let glyph_width = self.cur.glyph_width;
+        let changed = |i: usize| self.prev.glyph(i) != self.cur.glyph(i);
+        let mut cursor = Cursor { y: h, x: Some(0) };
+
+        for y in 0..h {
+            let row = y * w;
+            let mut x = 0;
+            while let Some(start) = (x..w).find(|&x| changed(row + x)) {
+                move_cursor(out, &mut cursor, y, start * glyph_width)?;
+
+                // Write a run of changed cells. Rewrite the unchanged cells
+                // in between if it's shorter than skipping them.
+                let mut end = start;
+                loop {
+                    out.write_str(self.cur.glyph(row + end))?;
+                    end += 1;
+
+                    let next = match (end..w).find(|&x| changed(row + x)) {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    let gap_len: usize = (end..next).map(|x| self.cur.glyph(row + x).len()).sum();
+                    if gap_len > cursor_forward_len((next - end) * glyph_width) {
+                        break;
+                    }
+                    for x in end..next {
+                        out.write_str(self.cur.glyph(row + x))?;
+                    }
+                    end = next;
+                }
+
+                cursor.x = if end < w {
+                    Some(end * glyph_width)
+                } else {
+                    None
+                };
+                x = end;
+            }
+        }
+
+        move_cursor(out, &mut cursor, h, 0)
+    }
+}
+
+/// Get the length of the escape sequence to move the cursor forward by `n`
+/// columns.
+fn cursor_forward_len(n: usize) -> usize {
+    let mut num_digits = 1;
+    let mut n = n / 10;
+    while n > 0 {
+        num_digits += 1;
+        n /= 10;
+    }
+    3 + num_digits
+}
+
+fn move_cursor(out: &mut impl fmt::Write, cursor: &mut Cursor, y: usize, x: usize) -> fmt::Result {
+    if y < cursor.y {
+        write!(out, "\x1b[{}A", cursor.y - y)?;
+    } else if y > cursor.y {
+        write!(out, "\x1b[{}B", y - cursor.y)?;
+    }
+
+    match cursor.x {
+        Some(cur_x) if cur_x == x => {}
+        Some(cur_x) if cur_x < x => write!(out, "\x1b[{}C", x - cur_x)?,
+        Some(cur_x) if x != 0 => write!(out, "\x1b[{}D", cur_x - x)?,
+        _ => {
+            out.write_char('\r')?;
+            if x != 0 {
+                write!(out, "\x1b[{}C", x)?;
+            }
+        }
+    }
+
+    *cursor = Cursor { y, x: Some(x) };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{set_spans_by_fn, Span, GLYPH_SET_1X2};
+
+    /// An image with "on" pixels at the specified positions
+    struct DotsImage([usize; 2], Vec<[usize; 2]>);
+
+    impl ImageRead for DotsImage {
+        fn dims(&self) -> [usize; 2] {
+            self.0
+        }
+
+        fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+            set_spans_by_fn(out, self.0[0], |x| self.1.contains(&[x, y]));
+        }
+    }
+
+    /// A minimal terminal emulator supporting the sequences used by
+    /// `LiveRenderer`. Each `char` occupies one column.
+    #[derive(Default)]
+    struct Screen {
+        lines: Vec<Vec<char>>,
+        cursor: [usize; 2],
+    }
+
+    impl Screen {
+        fn feed(&mut self, text: &str) {
+            let mut chars = text.chars();
+            while let Some(ch) = chars.next() {
+                let [x, y] = &mut self.cursor;
+                match ch {
+                    '\r' => *x = 0,
+                    '\n' => *y += 1,
+                    '\x1b' => {
+                        assert_eq!(chars.next(), Some('['));
+                        let mut n = 0;
+                        let command = loop {
+                            let ch = chars.next().unwrap();
+                            match ch.to_digit(10) {
+                                Some(digit) => n = n * 10 + digit as usize,
+                                None => break ch,
+                            }
+                        };
+                        match command {
+                            'A' => *y -= n,
+                            'B' => *y += n,
+                            'C' => *x += n,
+                            'D' => *x -= n,
+                            'J' => {
+                                self.lines.truncate(*y + 1);
+                                if let Some(line) = self.lines.get_mut(*y) {
+                                    line.truncate(*x);
+                                }
+                            }
+                            _ => panic!("unknown command {:?}", command),
+                        }
+                    }
+                    _ => {
+                        if self.lines.len() <= *y {
+                            self.lines.resize(*y + 1, Vec::new());
+                        }
+                        let line = &mut self.lines[*y];
+                        if line.len() <= *x {
+                            line.resize(*x + 1, ' ');
+                        }
+                        line[*x] = ch;
+                        *x += 1;
+                    }
+                }
+            }
+        }
+
+        fn text(&self) -> String {
+            self.lines
+                .iter()
+                .map(|line| line.iter().collect::<String>() + "\n")
+                .collect()
+        }
+    }
+
+    #[test]
+    fn render() {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_1X2;
+
+        let mut renderer = LiveRenderer::new();
+        let mut screen = Screen::default();
+
+        let frames = [
+            DotsImage([40, 6], vec![[0, 0], [5, 1], [39, 5]]),
+            // A small change
+            DotsImage([40, 6], vec![[0, 0], [6, 1], [39, 5]]),
+            // Changes close to each other
+            DotsImage([40, 6], vec![[0, 0], [6, 1], [8, 1], [10, 1], [39, 4]]),
+            // No change
+            DotsImage([40, 6], vec![[0, 0], [6, 1], [8, 1], [10, 1], [39, 4]]),
+            // Different dimensions
+            DotsImage([20, 4], vec![[19, 3]]),
+            DotsImage([20, 4], vec![[0, 3]]),
+        ];
+
+        let mut lens = Vec::new();
+        for image in frames.iter() {
+            let mut out = String::new();
+            renderer.render(image, &opts, &mut out).unwrap();
+            screen.feed(&out);
+            lens.push(out.len());
+
+            let mut expected = String::new();
+            Bmp2text::new()
+                .transform_and_write(image, &opts, &mut expected)
+                .unwrap();
+            assert_eq!(screen.text(), expected, "output = {:?}", out);
+            assert_eq!(screen.cursor, [0, image.0[1] / 2]);
+        }
+
+        // Unchanged frames only need little output
+        assert!(lens[1] < lens[0] / 4, "{:?}", lens);
+        assert_eq!(lens[3], 0);
+    }
+}
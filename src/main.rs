@@ -1,11 +1,23 @@
 use anyhow::{anyhow, bail, Context, Result};
 use clap::{Parser, ValueHint};
-use std::{convert::TryInto, io::prelude::*, path::PathBuf, str::FromStr, unreachable};
+use std::{
+    io::prelude::*,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+    unreachable,
+};
 
+mod asciicast;
+mod glyphsetfile;
 mod imageops;
+mod interrupt;
+mod playcount;
 
 #[derive(Parser, Debug)]
-#[clap(long_about = r"
+#[clap(
+    long_about = r"
 Image-to-text converter
 
                  ⠀⠀⠀⠀⠀⠀⠀⢠⣄⣠⣶⣤⣿⣤⣶⣄⣠⡄⠀⠀⠀⠀⠀⠀⠀
@@ -21,20 +33,39 @@ Image-to-text converter
                  ⠀⠀⠀⠀⠀⠀⠀⠈⠉⠘⠟⠙⠿⠋⠻⠃⠉⠁⠀⠀⠀⠀⠀⠀⠀
 
 (The above image was generated by this program with an option `-s 25`.)
-")]
+",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Opts {
-    /// The image to process
-    #[clap(name = "FILE", value_hint = ValueHint::AnyPath)]
-    image_path: PathBuf,
-    /// The glyph set to use
-    #[clap(short = 'g', default_value = "braille", value_enum)]
-    style: Style,
+    #[clap(subcommand)]
+    command: Option<Command>,
+    /// The image to process. Multiple images are treated as the frames of an
+    /// animation (requires `--animate` or `--asciicast`).
+    #[clap(name = "FILE", value_hint = ValueHint::AnyPath, required = true)]
+    image_paths: Vec<PathBuf>,
+    /// The glyph set to use: `slc`, `ms2x3`, `1x1`, `1x1-wide`, `1x2`, `2x2`,
+    /// `2x3`, `octant`, `braille`, `braille6`, `ramp`, `shade`, `ascii`,
+    /// `line`, `line-heavy`, `line-double`, or `@FILE` to load a glyph set
+    /// definition file
+    #[clap(short = 'g', default_value = "braille")]
+    style: StyleArg,
+    /// How braille glyph sets represent empty cells. Some services collapse or
+    /// trim U+2800 or spaces; `dot` avoids both.
+    #[clap(long = "braille-blank", default_value = "u2800", value_enum)]
+    braille_blank: BrailleBlank,
+    /// Rebuild the glyph set, choosing the glyphs for the patterns without
+    /// exact matches by the specified metric. `blur-l2` tends to work better
+    /// for stroke-heavy images.
+    #[clap(long = "glyph-metric", value_enum)]
+    glyph_metric: Option<GlyphMetric>,
     /// The width of output characters, only used when `-s` is given without
     /// `!`
     #[clap(short = 'w', default_value = "0.45")]
     cell_width: f64,
     /// The output size, measured in character cells or percent (e.g., `80`,
-    /// `80x40`, `80x40!`, `-80x40`, `100%`).
+    /// `80x40`, `80x40!`, `-80x40`, `100%`). A double-width glyph (e.g., in
+    /// `1x1-wide`) counts as two cells.
     /// [default: downscale to terminal size (if the output is a terminal) or
     /// 100% (otherwise)]
     ///
@@ -52,6 +83,17 @@ struct Opts {
     ///
     #[clap(short = 's')]
     out_size: Option<SizeSpec>,
+    /// Crop the image to the bounding box of the content (the "on" pixels
+    /// after thresholding or edge detection) before resizing, leaving the
+    /// specified number of pixels of padding around it.
+    #[clap(
+        long = "crop",
+        value_name = "PADDING",
+        default_missing_value = "0",
+        min_values = 0,
+        require_equals = true
+    )]
+    crop: Option<usize>,
 
     /// Specifies how to interpret the input image.
     #[clap(short = 'i', default_value = "auto", value_enum)]
@@ -72,9 +114,106 @@ struct Opts {
     /// `-i edge-canny`.
     #[clap(short = 'd', long = "dither")]
     dither: bool,
+    /// Diffuse the pixels each glyph gets wrong into the neighboring cells.
+    /// This preserves shades with glyph sets that can represent few patterns
+    /// exactly (e.g., `slc`). Combine with `-d` for gray shades.
+    #[clap(short = 'D', long = "glyph-dither")]
+    glyph_dither: bool,
     /// Choose the contrast enhancing technique to use for dithering.
     #[clap(long = "dither-contrast", default_value = "median-quant", value_enum)]
     dither_contrast: DitherContrast,
+    /// Color the output using the input image's colors. `auto` enables colors
+    /// if the output is a terminal that supports them. The color depth is
+    /// detected from `COLORTERM` and `TERM` unless explicitly specified.
+    #[clap(
+        long = "color",
+        value_name = "WHEN",
+        default_value = "never",
+        default_missing_value = "auto",
+        min_values = 0,
+        require_equals = true,
+        value_enum
+    )]
+    color: ColorWhen,
+    /// Set the background color of each character cell as well as the
+    /// foreground color. Only meaningful with `--color`.
+    #[clap(long = "color-bg")]
+    color_bg: bool,
+    /// Print the ratio of pixels exactly reproduced by the chosen glyphs to
+    /// the standard error after the output.
+    #[clap(long = "fidelity")]
+    fidelity: bool,
+    /// Output a heatmap of the reconstruction error of each cell instead of
+    /// glyphs: ` ` for exact cells, `1`-`9` for the number of mismatching
+    /// pixels, and `+` for more.
+    #[clap(long = "error-heatmap")]
+    error_heatmap: bool,
+    /// Terminate lines with CRLF instead of LF.
+    #[clap(long = "crlf")]
+    crlf: bool,
+    /// Remove whitespace and blank glyphs at the end of each line. Ignored
+    /// with `--color-bg`.
+    #[clap(long = "trim")]
+    trim: bool,
+    /// Omit blank lines at the top and bottom of the output. Ignored with
+    /// `--color-bg`.
+    #[clap(long = "strip-blank-lines")]
+    strip_blank_lines: bool,
+    /// Insert a string at the start of each line (e.g., `// ` to paste the
+    /// output into source code).
+    #[clap(long = "line-prefix", value_name = "PREFIX", default_value = "")]
+    line_prefix: String,
+    /// Play all frames of an animated GIF, APNG, or WebP image in place.
+    /// Press Ctrl-C to stop.
+    #[clap(long = "animate")]
+    animate: bool,
+    /// The number of times to play the animation (`0` = forever). Only
+    /// meaningful with `--animate`.
+    /// [default: the count specified by the (first) file, or 1]
+    #[clap(long = "loop", value_name = "COUNT")]
+    loop_count: Option<u32>,
+    /// Write the animation to the specified file as an asciicast v2 recording
+    /// (for asciinema players) instead of playing it. The input is decoded
+    /// like with `--animate`.
+    #[clap(
+        long = "asciicast",
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "animate"
+    )]
+    asciicast_path: Option<PathBuf>,
+    /// How long each frame given as a still image is displayed in an
+    /// animation, measured in milliseconds
+    #[clap(long = "frame-delay", value_name = "MS", default_value = "100")]
+    frame_delay: u64,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Convert text art back to an image by drawing each glyph's pattern
+    Text2bmp(Text2bmpOpts),
+}
+
+#[derive(clap::Args, Debug)]
+struct Text2bmpOpts {
+    /// The text to process (`-` for the standard input)
+    #[clap(name = "FILE", value_hint = ValueHint::FilePath)]
+    text_path: PathBuf,
+    /// The glyph set the text was produced with (see the main command's `-g`)
+    #[clap(short = 'g', default_value = "braille")]
+    style: StyleArg,
+    /// How braille glyph sets represent empty cells
+    #[clap(long = "braille-blank", default_value = "u2800", value_enum)]
+    braille_blank: BrailleBlank,
+    /// The PNG file to write
+    #[clap(short = 'o', value_hint = ValueHint::FilePath)]
+    output_path: PathBuf,
+    /// Draw each pixel as a square of this size
+    #[clap(long = "scale", default_value = "1")]
+    scale: u32,
+    /// Draw black-on-white instead of white-on-black
+    #[clap(long = "invert")]
+    invert: bool,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -82,22 +221,159 @@ enum Style {
     Slc,
     Ms2x3,
     _1x1,
+    /// Two-column glyphs for roughly square pixels
+    _1x1Wide,
     _1x2,
     _2x2,
     _2x3,
+    Octant,
+    /// Eight-dot braille patterns (2x4)
     Braille,
+    /// Six-dot braille patterns (2x3)
+    Braille6,
+    /// ASCII density ramp
+    Ramp,
+    /// Shade characters
+    Shade,
+    /// ASCII characters only
+    Ascii,
+    /// Box-drawing characters (use with `-i skeleton`)
+    Line,
+    /// Heavy box-drawing characters (use with `-i skeleton`)
+    LineHeavy,
+    /// Double box-drawing characters (use with `-i skeleton`)
+    LineDouble,
 }
 
 impl Style {
-    fn glyph_set(&self) -> &dyn img2text::GlyphSet {
+    fn glyph_set(&self) -> &'static dyn img2text::GlyphSet {
         match self {
             Self::Slc => img2text::GLYPH_SET_SLC,
             Self::Ms2x3 => img2text::GLYPH_SET_MS_2X3,
             Self::_1x1 => img2text::GLYPH_SET_1X1,
+            Self::_1x1Wide => img2text::GLYPH_SET_1X1_WIDE,
             Self::_1x2 => img2text::GLYPH_SET_1X2,
             Self::_2x2 => img2text::GLYPH_SET_2X2,
             Self::_2x3 => img2text::GLYPH_SET_2X3,
+            Self::Octant => img2text::GLYPH_SET_OCTANT,
             Self::Braille => img2text::GLYPH_SET_BRAILLE8,
+            Self::Braille6 => img2text::GLYPH_SET_BRAILLE6,
+            Self::Ramp => img2text::GLYPH_SET_RAMP,
+            Self::Shade => img2text::GLYPH_SET_SHADE,
+            Self::Ascii => img2text::GLYPH_SET_ASCII,
+            Self::Line => img2text::GLYPH_SET_LINE,
+            Self::LineHeavy => img2text::GLYPH_SET_LINE_HEAVY,
+            Self::LineDouble => img2text::GLYPH_SET_LINE_DOUBLE,
+        }
+    }
+
+    /// Get a flag indicating whether this glyph set represents intensity levels
+    /// and should be fed with a grayscale image.
+    fn is_luma(&self) -> bool {
+        matches!(self, Self::Ramp | Self::Shade)
+    }
+
+    /// Get a flag indicating whether this glyph set expects 1-pixel-wide
+    /// strokes (`-i skeleton`).
+    fn is_line_art(&self) -> bool {
+        matches!(self, Self::Line | Self::LineHeavy | Self::LineDouble)
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum BrailleBlank {
+    /// The blank braille pattern (U+2800)
+    U2800,
+    /// An ASCII space
+    Space,
+    /// The braille pattern dots-3 (`⠄`)
+    Dot,
+}
+
+impl From<BrailleBlank> for img2text::BrailleBlank {
+    fn from(x: BrailleBlank) -> Self {
+        match x {
+            BrailleBlank::U2800 => Self::Pattern,
+            BrailleBlank::Space => Self::Space,
+            BrailleBlank::Dot => Self::Dot,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum GlyphMetric {
+    /// Grow matches by dilation and erosion (the built-in glyph sets' method)
+    Mutation,
+    /// The number of mismatching pixels
+    Hamming,
+    /// Mismatching pixels weighted by their distance from the cell's edges
+    Weighted,
+    /// The L2 distance between the blurred patterns
+    BlurL2,
+}
+
+impl From<GlyphMetric> for img2text::GlyphMetric {
+    fn from(x: GlyphMetric) -> Self {
+        match x {
+            GlyphMetric::Mutation => Self::Mutation,
+            GlyphMetric::Hamming => Self::Hamming,
+            GlyphMetric::Weighted => Self::PositionWeighted,
+            GlyphMetric::BlurL2 => Self::BlurL2,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum StyleArg {
+    Builtin(Style),
+    /// A glyph set definition file (see `glyphsetfile`)
+    File(PathBuf),
+}
+
+impl FromStr for StyleArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix('@') {
+            Ok(Self::File(path.into()))
+        } else {
+            use clap::ValueEnum;
+            Style::from_str(s, false).map(Self::Builtin)
+        }
+    }
+}
+
+impl StyleArg {
+    /// Get the glyph set to use. `storage` receives the glyph set if it's
+    /// created at runtime.
+    fn glyph_set<'a>(
+        &self,
+        braille_blank: BrailleBlank,
+        storage: &'a mut Option<Box<dyn img2text::GlyphSet>>,
+    ) -> Result<&'a dyn img2text::GlyphSet> {
+        Ok(match self {
+            Self::Builtin(Style::Braille) => &**storage.insert(Box::new(
+                img2text::BrailleGlyphSet::eight_dot(braille_blank.into()),
+            )),
+            Self::Builtin(Style::Braille6) => &**storage.insert(Box::new(
+                img2text::BrailleGlyphSet::six_dot(braille_blank.into()),
+            )),
+            Self::Builtin(style) => style.glyph_set(),
+            Self::File(path) => &**storage.insert(Box::new(glyphsetfile::load(path)?)),
+        })
+    }
+
+    fn is_luma(&self) -> bool {
+        match self {
+            Self::Builtin(style) => style.is_luma(),
+            Self::File(_) => false,
+        }
+    }
+
+    fn is_line_art(&self) -> bool {
+        match self {
+            Self::Builtin(style) => style.is_line_art(),
+            Self::File(_) => false,
         }
     }
 }
@@ -112,6 +388,58 @@ enum InputTy {
     Bow,
     /// Canny edge detection
     EdgeCanny,
+    /// Thin the binarized image to 1-pixel-wide strokes. Intended for
+    /// diagrams and logos rendered with the `line*` glyph sets.
+    Skeleton,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+enum ColorWhen {
+    Never,
+    /// Enable colors if the output is a color terminal
+    Auto,
+    /// Always enable colors, detecting the color depth
+    Always,
+    /// The basic 16 colors
+    #[clap(name = "16")]
+    _16,
+    /// The xterm 256-color palette
+    #[clap(name = "256")]
+    _256,
+    /// 24-bit colors
+    Truecolor,
+}
+
+impl ColorWhen {
+    fn color_mode(&self, term: &console::Term) -> Option<img2text::ColorMode> {
+        match self {
+            Self::Never => None,
+            Self::Auto => {
+                if term.features().colors_supported() && console::colors_enabled() {
+                    Some(detect_color_mode())
+                } else {
+                    None
+                }
+            }
+            Self::Always => Some(detect_color_mode()),
+            Self::_16 => Some(img2text::ColorMode::Ansi16),
+            Self::_256 => Some(img2text::ColorMode::Ansi256),
+            Self::Truecolor => Some(img2text::ColorMode::Truecolor),
+        }
+    }
+}
+
+/// Guess the color depth supported by the terminal.
+fn detect_color_mode() -> img2text::ColorMode {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    let term = std::env::var("TERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        img2text::ColorMode::Truecolor
+    } else if term.contains("256color") {
+        img2text::ColorMode::Ansi256
+    } else {
+        img2text::ColorMode::Ansi16
+    }
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -201,18 +529,96 @@ fn main() -> Result<()> {
     let mut opts = Opts::parse();
     log::debug!("opts = {:#?}", opts);
 
-    // Open the image
-    let img = image::open(&opts.image_path).with_context(|| {
-        format!(
-            "Failed to read an input image from '{}'",
-            opts.image_path.display()
-        )
-    })?;
-    let mut img = img.into_luma8();
+    if let Some(Command::Text2bmp(t2b_opts)) = &opts.command {
+        return text2bmp(t2b_opts);
+    }
+
+    // The flag that enables animations, if any
+    let animation_flag = if opts.asciicast_path.is_some() {
+        Some("--asciicast")
+    } else if opts.animate {
+        Some("--animate")
+    } else {
+        None
+    };
+
+    if animation_flag.is_none() && opts.image_paths.len() > 1 {
+        bail!("multiple input images require `--animate` or `--asciicast`");
+    }
+
+    // Open the image(s)
+    let mut frames = Vec::new();
+    let mut num_plays = None;
+    for image_path in opts.image_paths.iter() {
+        let (decoded, file_num_plays) = if animation_flag.is_some() {
+            decode_frames(image_path, Duration::from_millis(opts.frame_delay))
+        } else {
+            image::open(image_path).map(|img| (vec![(img, Duration::ZERO)], 1))
+        }
+        .with_context(|| {
+            format!(
+                "Failed to read an input image from '{}'",
+                image_path.display()
+            )
+        })?;
+        if decoded.is_empty() {
+            bail!("'{}' contains no frames", image_path.display());
+        }
+        frames.extend(decoded);
+        num_plays.get_or_insert(file_num_plays);
+    }
+    log::debug!("decoded {} frame(s)", frames.len());
+
+    if frames
+        .iter()
+        .any(|(img, _)| [img.width(), img.height()] != [frames[0].0.width(), frames[0].0.height()])
+    {
+        bail!("all frames must have the same size");
+    }
 
     // Options
+    let line_prefix = opts.line_prefix.clone();
     let mut b2t_opts = img2text::Bmp2textOpts::new();
-    b2t_opts.glyph_set = opts.style.glyph_set();
+    let mut glyph_set_storage = None;
+    b2t_opts.glyph_set = opts
+        .style
+        .glyph_set(opts.braille_blank, &mut glyph_set_storage)?;
+
+    let rebuilt_glyph_set;
+    if let Some(metric) = opts.glyph_metric {
+        let mut builder = img2text::GlyphSetBuilder::from_glyph_set(b2t_opts.glyph_set);
+        if builder.glyphs().next().is_some() {
+            rebuilt_glyph_set = builder
+                .metric(metric.into())
+                .build()
+                .with_context(|| "Failed to rebuild the glyph set")?;
+            b2t_opts.glyph_set = &rebuilt_glyph_set;
+        } else {
+            log::warn!("`--glyph-metric` is not supported by the glyph set; ignoring");
+        }
+    }
+
+    let console_stdout = console::Term::stdout();
+    b2t_opts.color = opts.color.color_mode(&console_stdout).map(|mode| {
+        let mut color_opts = img2text::ColorOpts::new(mode);
+        color_opts.background = opts.color_bg;
+        color_opts
+    });
+    log::debug!("color = {:?}", b2t_opts.color);
+
+    b2t_opts.line_terminator = if opts.crlf {
+        img2text::LineTerminator::CrLf
+    } else {
+        img2text::LineTerminator::Lf
+    };
+    b2t_opts.trim_trailing_whitespace = opts.trim;
+    b2t_opts.strip_blank_rows = opts.strip_blank_lines;
+    b2t_opts.line_prefix = &line_prefix;
+
+    b2t_opts.error_diffusion = opts.glyph_dither;
+    if opts.glyph_dither && opts.style.is_luma() {
+        log::warn!("`--glyph-dither` is not supported by the glyph set; ignoring");
+    }
 
     if !opts.cell_width.is_finite() || opts.cell_width <= 0.1 || opts.cell_width > 10.0 {
         bail!("cell_width is out of range");
@@ -240,8 +646,84 @@ fn main() -> Result<()> {
         bail!("`--dither` and `-i edge-canny` are incompatible");
     }
 
+    if opts.dither && opts.input_ty == InputTy::Skeleton {
+        bail!("`--dither` and `-i skeleton` are incompatible");
+    }
+
+    let measure_errors = opts.fidelity || opts.error_heatmap;
+    if measure_errors && opts.style.is_luma() {
+        bail!("`--fidelity` and `--error-heatmap` are not supported by the glyph set");
+    }
+
+    if measure_errors && b2t_opts.color.is_some() {
+        bail!("`--fidelity` and `--error-heatmap` are incompatible with `--color`");
+    }
+
+    if let Some(flag) = animation_flag {
+        if opts.style.is_luma() {
+            bail!("`{}` is not supported by the glyph set", flag);
+        }
+
+        if b2t_opts.color.is_some() {
+            bail!("`{}` is incompatible with `--color`", flag);
+        }
+
+        if measure_errors {
+            bail!(
+                "`--fidelity` and `--error-heatmap` are incompatible with `{}`",
+                flag
+            );
+        }
+
+        if opts.crlf || opts.trim || opts.strip_blank_lines || !opts.line_prefix.is_empty() {
+            log::warn!("the line formatting options are ignored with `{}`", flag);
+        }
+    }
+
+    // Keep the colors only if they are needed
+    let mut frames: Vec<Frame> = frames
+        .into_iter()
+        .map(|(img, delay)| Frame {
+            color_img: b2t_opts.color.map(|_| img.to_rgb8()),
+            img: img.into_luma8(),
+            delay,
+            threshold: 128,
+            invert: false,
+        })
+        .collect();
+
+    // Crop the image to the content
+    if let Some(padding) = opts.crop {
+        // Decide `input_ty` now so that the decision is based on the whole
+        // image
+        resolve_input_ty(&mut opts, &frames[0].img);
+
+        // Keep the content of all frames
+        let bbox = frames
+            .iter()
+            .filter_map(|frame| content_bbox(&opts, &frame.img, padding))
+            .reduce(|[xs0, ys0], [xs1, ys1]| {
+                [
+                    xs0.start.min(xs1.start)..xs0.end.max(xs1.end),
+                    ys0.start.min(ys1.start)..ys0.end.max(ys1.end),
+                ]
+            });
+
+        if let Some([xs, ys]) = bbox {
+            log::debug!("cropping the image to {:?}", [&xs, &ys]);
+            let [x, y, w, h] = [xs.start, ys.start, xs.len(), ys.len()].map(|x| x as u32);
+            for frame in frames.iter_mut() {
+                frame.img = image::imageops::crop_imm(&frame.img, x, y, w, h).to_image();
+                if let Some(color_img) = &mut frame.color_img {
+                    *color_img = image::imageops::crop_imm(color_img, x, y, w, h).to_image();
+                }
+            }
+        } else {
+            log::warn!("the image has no content to crop to; ignoring `--crop`");
+        }
+    }
+
     // Resize the image to the terminal size if the size is not specified
-    let console_stdout = console::Term::stdout();
     if opts.out_size.is_none() && console_stdout.features().is_attended() {
         if let Some((h, w)) = console_stdout.size_checked() {
             let h = h.saturating_sub(3);
@@ -257,7 +739,8 @@ fn main() -> Result<()> {
         }
     }
 
-    // Resize the image if requested
+    // Resize the image if requested. All frames have the same size.
+    let (img_w, img_h) = frames[0].img.dimensions();
     if let Some(out_size) = &opts.out_size {
         let in_dims = match out_size {
             SizeSpec::Absolute {
@@ -270,7 +753,7 @@ fn main() -> Result<()> {
                 dims,
                 mode: SizeMode::Contain,
             } => img2text::adjust_image_size_for_output_size_preserving_aspect_ratio(
-                [img.width() as _, img.height() as _],
+                [img_w as _, img_h as _],
                 *dims,
                 true,
                 false, // contain
@@ -283,7 +766,7 @@ fn main() -> Result<()> {
                 dims,
                 mode: SizeMode::ScaleDown,
             } => img2text::adjust_image_size_for_output_size_preserving_aspect_ratio(
-                [img.width() as _, img.height() as _],
+                [img_w as _, img_h as _],
                 *dims,
                 false,
                 false, // contain
@@ -293,8 +776,8 @@ fn main() -> Result<()> {
             .ok_or_else(|| anyhow!("requested size is too large"))?,
 
             SizeSpec::Relative(ratio) => {
-                let w = img.width() as f64 * ratio;
-                let h = img.height() as f64 * ratio;
+                let w = img_w as f64 * ratio;
+                let h = img_h as f64 * ratio;
                 if w > u32::MAX as f64 || h > u32::MAX as f64 {
                     bail!("requested size is too large");
                 }
@@ -304,21 +787,29 @@ fn main() -> Result<()> {
 
         let in_dims = [in_dims[0] as u32, in_dims[1] as u32];
 
-        if img.dimensions() != (in_dims[0], in_dims[1]) {
+        if (img_w, img_h) != (in_dims[0], in_dims[1]) {
             log::debug!(
                 "resampling the image from {:?} to {:?}",
-                match img.dimensions() {
-                    (x, y) => [x, y],
-                },
+                [img_w, img_h],
                 in_dims
             );
 
-            img = image::imageops::resize(
-                &img,
-                in_dims[0],
-                in_dims[1],
-                image::imageops::FilterType::CatmullRom,
-            );
+            for frame in frames.iter_mut() {
+                frame.img = image::imageops::resize(
+                    &frame.img,
+                    in_dims[0],
+                    in_dims[1],
+                    image::imageops::FilterType::CatmullRom,
+                );
+                if let Some(color_img) = &mut frame.color_img {
+                    *color_img = image::imageops::resize(
+                        color_img,
+                        in_dims[0],
+                        in_dims[1],
+                        image::imageops::FilterType::CatmullRom,
+                    );
+                }
+            }
         } else {
             log::debug!(
                 "refusing to resample the image to the identical size ({:?})",
@@ -329,61 +820,226 @@ fn main() -> Result<()> {
 
     log::debug!(
         "expected output size for image of size {:?} is {:?}",
-        match img.dimensions() {
+        match frames[0].img.dimensions() {
             (x, y) => [x, y],
         },
         [
-            img2text::num_glyphs_for_image_width(img.width() as _, &b2t_opts),
-            img2text::num_lines_for_image_height(img.height() as _, &b2t_opts),
+            img2text::num_columns_for_image_width(frames[0].img.width() as _, &b2t_opts),
+            img2text::num_lines_for_image_height(frames[0].img.height() as _, &b2t_opts),
         ]
     );
 
-    // Auto-threshold
-    let mut histogram = [0; 256];
-    imageops::accumulate_histogram(
-        &mut histogram,
-        img.pixels().map(|&image::Luma([luma])| luma),
-    );
-    log::trace!("histogram = {:?}", histogram);
-    let mut threshold = if let Some(x) = imageops::find_threshold(&histogram) {
-        log::debug!("threshold = {}", x);
-        x
+    // Decide `input_ty` based on the first frame so that it doesn't change
+    // in the middle of an animation
+    resolve_input_ty(&mut opts, &frames[0].img);
+
+    for frame in frames.iter_mut() {
+        binarize(&opts, frame);
+    }
+
+    if let Some(path) = &opts.asciicast_path {
+        return record_asciicast(path, &b2t_opts, &frames);
+    }
+
+    if opts.animate {
+        let num_plays = opts.loop_count.or(num_plays).unwrap();
+        return play(num_plays, &b2t_opts, &frames, &console_stdout);
+    }
+
+    // Process the image, writing each line as soon as it's ready
+    let frame = &frames[0];
+    let img_proxy = frame.image_read();
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut write_line = |line: &str| stdout.write_all(line.as_bytes());
+
+    let mut b2t = img2text::Bmp2text::new();
+    let mut error_stats = img2text::ErrorStats::new();
+    if opts.style.is_luma() {
+        if b2t_opts.color.is_some() {
+            log::warn!("`--color` is not supported by the glyph set; ignoring");
+        }
+        b2t.transform_luma_lines(&img_proxy, &b2t_opts, write_line)
+    } else if let Some(color_img) = &frame.color_img {
+        b2t.transform_lines_colored(&img_proxy, color_img, &b2t_opts, write_line)
+    } else if measure_errors {
+        let mut heatmap_line = String::new();
+        let glyph_width = b2t_opts.glyph_set.glyph_width();
+        b2t.transform_lines_with_errors(&img_proxy, &b2t_opts, &mut error_stats, |line, errors| {
+            if opts.error_heatmap {
+                heatmap_line.clear();
+                heatmap_line.push_str(b2t_opts.line_prefix);
+                for &error in errors {
+                    heatmap_line.push(match error {
+                        Some(0) => ' ',
+                        Some(x @ 1..=9) => (b'0' + x as u8) as char,
+                        Some(_) => '+',
+                        None => '?',
+                    });
+                    // Keep the columns aligned with the glyphs
+                    heatmap_line.extend(std::iter::repeat(' ').take(glyph_width - 1));
+                }
+                heatmap_line.push_str(b2t_opts.line_terminator.as_str());
+                write_line(&heatmap_line)
+            } else {
+                write_line(line)
+            }
+        })
     } else {
-        log::debug!("couldn't find the threshold, using the default value 128");
-        128
+        img2text::par_transform_lines(&img_proxy, &b2t_opts, write_line)
+    }
+    .with_context(|| "Failed to write the output to the standard output")?;
+
+    if opts.fidelity {
+        let stats = &error_stats;
+        eprintln!(
+            "fidelity: {:.2}% ({} of {} pixels differ, {} of {} cells exact, \
+             max cell error {})",
+            stats.fidelity().unwrap_or(1.0) * 100.0,
+            stats.num_error_pixels,
+            stats.num_pixels,
+            stats.num_exact_cells,
+            stats.num_cells,
+            stats.max_cell_error,
+        );
+    }
+
+    Ok(())
+}
+
+/// A frame of the input image
+struct Frame {
+    img: image::GrayImage,
+    /// Present only if colors are needed
+    color_img: Option<image::RgbImage>,
+    /// How long the frame is displayed in an animation
+    delay: Duration,
+    /// Set by `binarize`
+    threshold: u8,
+    /// Set by `binarize`
+    invert: bool,
+}
+
+impl Frame {
+    fn image_read(&self) -> img2text::image::GrayImageRead<'_> {
+        let mut img_proxy = img2text::image::GrayImageRead::new(
+            self.img.as_raw(),
+            [self.img.width() as usize, self.img.height() as usize],
+        );
+        img_proxy.threshold = self.threshold;
+        img_proxy.invert = self.invert;
+        img_proxy
+    }
+}
+
+/// Decode all frames of an animated GIF, APNG, or WebP image along with their
+/// delays and the number of times the animation should be played (`0` =
+/// forever). Other images are decoded as a single frame with `still_delay`
+/// to be played once.
+///
+/// Like web browsers do, delays shorter than 20 milliseconds are replaced with
+/// 100 milliseconds because many animations specify zero delays.
+fn decode_frames(
+    path: &Path,
+    still_delay: Duration,
+) -> image::ImageResult<(Vec<(image::DynamicImage, Duration)>, u32)> {
+    use image::{codecs, AnimationDecoder, DynamicImage, ImageFormat};
+
+    let data = std::fs::read(path)?;
+    let reader = image::io::Reader::new(std::io::Cursor::new(&data[..])).with_guessed_format()?;
+    let (animation, num_plays) = match reader.format() {
+        Some(ImageFormat::Gif) => (
+            codecs::gif::GifDecoder::new(reader.into_inner())?.into_frames(),
+            playcount::gif(&data),
+        ),
+        Some(ImageFormat::Png) => {
+            let decoder = codecs::png::PngDecoder::new(reader.into_inner())?;
+            if !decoder.is_apng() {
+                let img = DynamicImage::from_decoder(decoder)?;
+                return Ok((vec![(img, still_delay)], 1));
+            }
+            (decoder.apng().into_frames(), playcount::png(&data))
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = codecs::webp::WebPDecoder::new(reader.into_inner())?;
+            if !decoder.has_animation() {
+                let img = DynamicImage::from_decoder(decoder)?;
+                return Ok((vec![(img, still_delay)], 1));
+            }
+            (decoder.into_frames(), playcount::webp(&data))
+        }
+        _ => return Ok((vec![(reader.decode()?, still_delay)], 1)),
     };
+    log::debug!(
+        "play count of '{}' = {} (0 = forever)",
+        path.display(),
+        num_plays
+    );
 
-    // black-on-white/white-on-black detection
-    let omega0: u32 = histogram[..threshold].iter().sum();
-    let omega1: u32 = histogram[threshold..].iter().sum();
+    let frames = animation
+        .map(|frame| {
+            let frame = frame?;
+            let mut delay = Duration::from(frame.delay());
+            if delay < Duration::from_millis(20) {
+                delay = Duration::from_millis(100);
+            }
+            Ok((DynamicImage::ImageRgba8(frame.into_buffer()), delay))
+        })
+        .collect::<image::ImageResult<_>>()?;
+    Ok((frames, num_plays))
+}
+
+/// Resolve `-i auto` based on `img`.
+fn resolve_input_ty(opts: &mut Opts, img: &image::GrayImage) {
     if opts.input_ty == InputTy::Auto {
-        let omega_min = omega0.min(omega1);
-        let omega_max = omega0.max(omega1);
-        log::debug!("[omega_min, omega_max] = {:?}", [omega_min, omega_max]);
+        let (histogram, threshold) = auto_threshold(img);
+        let omega0: u32 = histogram[..threshold].iter().sum();
+        let omega1: u32 = histogram[threshold..].iter().sum();
+        opts.input_ty = guess_input_ty(&opts.style, omega0, omega1);
+    }
+}
 
-        // TODO: probably should take line thickness into account when detecting
-        //       line art
-        opts.input_ty = if omega_min * 4 > omega_max {
-            log::debug!(
-                "there are roughly the same numbers of black and white \
-                pixels. this indicates the input image is not a line art, so \
-                we will use `edge-canny` (the Canny edge detector)."
+/// Find the bounding box of the content (the "on" pixels after thresholding
+/// or edge detection) of `img` for `--crop`. `opts.input_ty` must be resolved.
+fn content_bbox(opts: &Opts, img: &image::GrayImage, padding: usize) -> Option<[Range<usize>; 2]> {
+    let dims = [img.width() as usize, img.height() as usize];
+    if opts.input_ty == InputTy::EdgeCanny {
+        if dims[0] != 0 && dims[1] != 0 {
+            let edges = imageproc::edges::canny(
+                img,
+                opts.edge_canny_low_threshold,
+                opts.edge_canny_high_threshold,
             );
-            InputTy::EdgeCanny
+            img2text::image::content_bbox(&edges, padding)
         } else {
-            log::debug!(
-                "the numbers of black and white pixels are remarkably different.
-                this indicates the input image is a line art, so \
-                we will not use the edge detector."
-            );
-            if omega1 > omega0 {
-                InputTy::Bow
-            } else {
-                InputTy::Wob
-            }
+            None
+        }
+    } else {
+        let (histogram, threshold) = auto_threshold(img);
+        let omega0: u32 = histogram[..threshold].iter().sum();
+        let omega1: u32 = histogram[threshold..].iter().sum();
+
+        let mut img_proxy = img2text::image::GrayImageRead::new(img.as_raw(), dims);
+        img_proxy.threshold = threshold as u8;
+        img_proxy.invert = match opts.input_ty {
+            InputTy::Bow => true,
+            InputTy::Wob => false,
+            // The minority is the foreground
+            InputTy::Skeleton => omega0 < omega1,
+            InputTy::Auto | InputTy::EdgeCanny => unreachable!(),
         };
-        log::debug!("guessed input_ty = {:?}", opts.input_ty);
+        img2text::image::content_bbox(&img_proxy, padding)
     }
+}
+
+/// Apply thresholding, edge detection, thinning, and dithering to
+/// `frame.img` as specified by `opts`, and set `frame.threshold` and
+/// `frame.invert`. `opts.input_ty` must be resolved.
+fn binarize(opts: &Opts, frame: &mut Frame) {
+    let img = &mut frame.img;
+
+    // Auto-threshold
+    let (histogram, mut threshold) = auto_threshold(img);
 
     let invert = match opts.input_ty {
         InputTy::Bow => true,
@@ -391,19 +1047,25 @@ fn main() -> Result<()> {
         InputTy::Auto => unreachable!(),
         InputTy::EdgeCanny => {
             if img.width() != 0 && img.height() != 0 {
-                img = imageproc::edges::canny(
-                    &img,
+                *img = imageproc::edges::canny(
+                    img,
                     opts.edge_canny_low_threshold,
                     opts.edge_canny_high_threshold,
                 );
             }
             false
         }
+        InputTy::Skeleton => {
+            imageops::binarize_and_skeletonize(img, threshold);
+            threshold = 128;
+            false
+        }
     };
 
     // Apply dithering.
-    // `-i auto` can imply `-i edge-canny`, in which case just ignore `--dither`.
-    if opts.dither && opts.input_ty != InputTy::EdgeCanny {
+    // `-i auto` can imply `-i edge-canny` or `-i skeleton`, in which case just
+    // ignore `--dither`.
+    if opts.dither && !matches!(opts.input_ty, InputTy::EdgeCanny | InputTy::Skeleton) {
         let mut palette = [0, 255];
 
         match opts.dither_contrast {
@@ -426,7 +1088,7 @@ fn main() -> Result<()> {
         log::debug!("dithering quantization threshold = {:?}", threshold);
 
         image::imageops::colorops::dither(
-            &mut img,
+            img,
             &BlackWhiteColorMap {
                 threshold: threshold as u8,
                 palette,
@@ -434,51 +1096,238 @@ fn main() -> Result<()> {
         );
     }
 
-    // Process the image
-    use img2text::ImageRead;
-    let img_proxy = GrayImageRead {
-        image: &img,
-        threshold,
-        invert,
+    frame.threshold = threshold as u8;
+    frame.invert = invert;
+}
+
+/// Implements `--animate`. Plays `frames` in place `num_plays` times (`0` =
+/// forever) or until Ctrl-C is pressed.
+fn play(
+    num_plays: u32,
+    b2t_opts: &img2text::Bmp2textOpts,
+    frames: &[Frame],
+    term: &console::Term,
+) -> Result<()> {
+    /// Shows the cursor when dropped
+    struct ShowCursorOnDrop<'a>(&'a console::Term);
+
+    impl Drop for ShowCursorOnDrop<'_> {
+        fn drop(&mut self) {
+            let _ = self.0.show_cursor();
+        }
+    }
+
+    // Hide the cursor during playback unless Ctrl-C would leave it hidden
+    let _show_cursor_on_drop = if interrupt::install_handler() {
+        term.hide_cursor()
+            .with_context(|| "Failed to write the output to the standard output")?;
+        Some(ShowCursorOnDrop(term))
+    } else {
+        None
     };
-    let mut out_buffer = String::with_capacity(
-        img2text::max_output_len_for_image_dims(img_proxy.dims(), &b2t_opts)
-            .ok_or_else(|| anyhow!("image is too large"))?,
-    );
 
-    img2text::Bmp2text::new()
-        .transform_and_write(&img_proxy, &b2t_opts, &mut out_buffer)
-        .unwrap();
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut renderer = img2text::LiveRenderer::new();
+    let mut out = String::new();
+    let mut deadline = Instant::now();
+    let mut num_loops = 0;
+
+    'outer: loop {
+        for frame in frames {
+            out.clear();
+            renderer
+                .render(&frame.image_read(), b2t_opts, &mut out)
+                .unwrap();
+            stdout
+                .write_all(out.as_bytes())
+                .and_then(|()| stdout.flush())
+                .with_context(|| "Failed to write the output to the standard output")?;
 
-    std::io::stdout()
-        .write(out_buffer.as_bytes())
-        .with_context(|| "Failed to write the output to the standard output")?;
+            // Nothing to animate
+            if frames.len() == 1 {
+                break 'outer;
+            }
+
+            deadline += frame.delay;
+            if !sleep_until(deadline) {
+                log::debug!("interrupted");
+                break 'outer;
+            }
+        }
+
+        num_loops += 1;
+        if num_loops == num_plays {
+            break;
+        }
+    }
 
     Ok(())
 }
 
-struct GrayImageRead<'a> {
-    image: &'a image::GrayImage,
-    threshold: usize,
-    invert: bool,
+/// Implements `--asciicast`. Writes `frames` to `path` as an asciicast v2
+/// recording of what `--animate` prints for one loop.
+fn record_asciicast(
+    path: &Path,
+    b2t_opts: &img2text::Bmp2textOpts,
+    frames: &[Frame],
+) -> Result<()> {
+    let [img_w, img_h] = [frames[0].img.width(), frames[0].img.height()];
+    let dims = [
+        img2text::num_columns_for_image_width(img_w as _, b2t_opts),
+        // Plus the line the cursor is left at
+        img2text::num_lines_for_image_height(img_h as _, b2t_opts) + 1,
+    ];
+    log::debug!("asciicast terminal size = {:?}", dims);
+
+    let write = || -> std::io::Result<()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut writer = asciicast::Writer::new(file, dims)?;
+        let mut renderer = img2text::LiveRenderer::new();
+        let mut out = String::new();
+        let mut time = Duration::ZERO;
+
+        // Hide the cursor during playback
+        out.push_str("\x1b[?25l");
+
+        for frame in frames {
+            renderer
+                .render(&frame.image_read(), b2t_opts, &mut out)
+                .unwrap();
+            // Skip the frames identical to the previous ones
+            if !out.is_empty() {
+                writer.output(time, &out)?;
+            }
+            out.clear();
+            time += frame.delay;
+        }
+
+        // Show the cursor after the last frame's delay
+        writer.output(time, "\x1b[?25h")?;
+        writer.into_inner().flush()
+    };
+
+    write().with_context(|| format!("Failed to write an asciicast to '{}'", path.display()))
 }
 
-impl img2text::ImageRead for GrayImageRead<'_> {
-    fn dims(&self) -> [usize; 2] {
-        let (w, h) = self.image.dimensions();
-        [w.try_into().unwrap(), h.try_into().unwrap()]
+/// Sleep until `deadline`. Returns `false` if interrupted by Ctrl-C.
+fn sleep_until(deadline: Instant) -> bool {
+    loop {
+        if interrupt::is_interrupted() {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        // Wake up periodically to check for Ctrl-C
+        std::thread::sleep((deadline - now).min(Duration::from_millis(50)));
     }
+}
 
-    fn copy_line_as_spans_to(&self, y: usize, out: &mut [img2text::Span]) {
-        let Self {
-            image,
-            threshold,
-            invert,
-        } = *self;
-        img2text::set_spans_by_fn(out, self.dims()[0], move |x| {
-            (image[(x as u32, y as u32)].0[0] as usize >= threshold) ^ invert
-        });
+/// Find the binarization threshold of `img`. Returns the histogram and the
+/// threshold.
+fn auto_threshold(img: &image::GrayImage) -> ([u32; 256], usize) {
+    let mut histogram = [0; 256];
+    imageops::accumulate_histogram(
+        &mut histogram,
+        img.pixels().map(|&image::Luma([luma])| luma),
+    );
+    log::trace!("histogram = {:?}", histogram);
+    let threshold = if let Some(x) = imageops::find_threshold(&histogram) {
+        log::debug!("threshold = {}", x);
+        x
+    } else {
+        log::debug!("couldn't find the threshold, using the default value 128");
+        128
+    };
+    (histogram, threshold)
+}
+
+/// Guess the appropriate `InputTy` from the numbers of pixels below
+/// (`omega0`) and above (`omega1`) the threshold.
+fn guess_input_ty(style: &StyleArg, omega0: u32, omega1: u32) -> InputTy {
+    let omega_min = omega0.min(omega1);
+    let omega_max = omega0.max(omega1);
+    log::debug!("[omega_min, omega_max] = {:?}", [omega_min, omega_max]);
+
+    // TODO: probably should take line thickness into account when detecting
+    //       line art
+    let input_ty = if style.is_line_art() {
+        log::debug!("the glyph set expects 1-pixel-wide strokes, so we will use `skeleton`");
+        InputTy::Skeleton
+    } else if omega_min * 4 > omega_max && style.is_luma() {
+        log::debug!(
+            "there are roughly the same numbers of black and white \
+            pixels, but the glyph set can represent gray shades, so \
+            we will use the image as-is."
+        );
+        InputTy::Wob
+    } else if omega_min * 4 > omega_max {
+        log::debug!(
+            "there are roughly the same numbers of black and white \
+            pixels. this indicates the input image is not a line art, so \
+            we will use `edge-canny` (the Canny edge detector)."
+        );
+        InputTy::EdgeCanny
+    } else {
+        log::debug!(
+            "the numbers of black and white pixels are remarkably different.
+            this indicates the input image is a line art, so \
+            we will not use the edge detector."
+        );
+        if omega1 > omega0 {
+            InputTy::Bow
+        } else {
+            InputTy::Wob
+        }
+    };
+    log::debug!("guessed input_ty = {:?}", input_ty);
+    input_ty
+}
+
+/// Implements the `text2bmp` subcommand.
+fn text2bmp(opts: &Text2bmpOpts) -> Result<()> {
+    let mut glyph_set_storage = None;
+    let glyph_set = opts
+        .style
+        .glyph_set(opts.braille_blank, &mut glyph_set_storage)?;
+
+    let text = if opts.text_path.as_os_str() == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .with_context(|| "Failed to read the standard input")?;
+        text
+    } else {
+        std::fs::read_to_string(&opts.text_path)
+            .with_context(|| format!("Failed to read '{}'", opts.text_path.display()))?
+    };
+
+    let bitmap = img2text::text2bmp(&text, glyph_set)?;
+
+    if opts.scale == 0 {
+        bail!("scale must be positive");
     }
+    let [width, height] = bitmap.dims();
+    let [on, off] = if opts.invert { [0, 255] } else { [255, 0] };
+    let img = image::GrayImage::from_fn(
+        (width as u32)
+            .checked_mul(opts.scale)
+            .ok_or_else(|| anyhow!("output is too large"))?,
+        (height as u32)
+            .checked_mul(opts.scale)
+            .ok_or_else(|| anyhow!("output is too large"))?,
+        |x, y| {
+            let on_pixel = bitmap.get([(x / opts.scale) as usize, (y / opts.scale) as usize]);
+            image::Luma([if on_pixel { on } else { off }])
+        },
+    );
+
+    img.save_with_format(&opts.output_path, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write '{}'", opts.output_path.display()))?;
+
+    Ok(())
 }
 
 struct BlackWhiteColorMap {
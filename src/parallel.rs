@@ -0,0 +1,110 @@
+//! Multi-threaded conversion
+use rayon::prelude::*;
+use std::convert::Infallible;
+
+use crate::{
+    num_lines_for_image_height, postprocess::LineFilter, Bmp2text, Bmp2textOpts, ImageRead,
+};
+
+/// The number of output lines converted by one task
+const BAND_LEN: usize = 16;
+
+/// Like [`Bmp2text::transform_lines`], but splits the output lines into bands
+/// and converts them concurrently on the Rayon thread pool. `f` is called with
+/// each output line in order.
+///
+/// The bands are processed a few at a time per thread, so the memory usage is
+/// bounded regardless of the image height.
+///
+/// If [`Bmp2textOpts::error_diffusion`] is enabled, this function converts the
+/// image sequentially because each line depends on the previous one.
+pub fn par_transform_lines<E>(
+    image: &(impl ImageRead + Sync),
+    opts: &Bmp2textOpts,
+    mut f: impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    if opts.error_diffusion {
+        return Bmp2text::new().transform_lines(image, opts, f);
+    }
+
+    let out_h = num_lines_for_image_height(image.dims()[1], opts);
+    let num_bands = (out_h + BAND_LEN - 1) / BAND_LEN;
+    let bands_per_round = rayon::current_num_threads() * 2;
+
+    let mut bands: Vec<String> = Vec::with_capacity(bands_per_round);
+    let mut filter = LineFilter::new(opts);
+
+    for round_start in (0..num_bands).step_by(bands_per_round) {
+        let round = round_start..(round_start + bands_per_round).min(num_bands);
+
+        round
+            .into_par_iter()
+            .map_init(Bmp2text::new, |b2t, band_i| {
+                let out_ys = band_i * BAND_LEN..((band_i + 1) * BAND_LEN).min(out_h);
+                let mut band = String::new();
+                b2t.transform_line_range(image, opts, out_ys, |line| {
+                    band.push_str(line);
+                    band.push('\n');
+                    Ok::<_, Infallible>(())
+                })
+                .unwrap();
+                band
+            })
+            .collect_into_vec(&mut bands);
+
+        for line in bands.iter().flat_map(|band| band.split_terminator('\n')) {
+            filter.push(line, &mut (), &mut |line, _| f(line))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`Bmp2text::transform_and_write`], but converts the image
+/// concurrently. See [`par_transform_lines`].
+pub fn par_transform_and_write(
+    image: &(impl ImageRead + Sync),
+    opts: &Bmp2textOpts,
+    out: &mut impl std::fmt::Write,
+) -> std::fmt::Result {
+    par_transform_lines(image, opts, |line| out.write_str(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testutil::NoiseImage, GlyphSet};
+
+    #[test]
+    fn matches_sequential() {
+        let image = NoiseImage([123, 457], 2);
+        let glyph_sets: [&dyn GlyphSet; 3] = [
+            crate::GLYPH_SET_BRAILLE8,
+            crate::GLYPH_SET_MS_2X3,
+            crate::GLYPH_SET_SLC,
+        ];
+        for (&glyph_set, &post_process) in glyph_sets
+            .iter()
+            .flat_map(|gs| [false, true].iter().map(move |pp| (gs, pp)))
+        {
+            let mut opts = Bmp2textOpts::new();
+            opts.glyph_set = glyph_set;
+            if post_process {
+                opts.line_terminator = crate::LineTerminator::CrLf;
+                opts.trim_trailing_whitespace = true;
+                opts.strip_blank_rows = true;
+                opts.line_prefix = "# ";
+            }
+
+            let mut expected = String::new();
+            Bmp2text::new()
+                .transform_and_write(&image, &opts, &mut expected)
+                .unwrap();
+
+            let mut actual = String::new();
+            par_transform_and_write(&image, &opts, &mut actual).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+    }
+}
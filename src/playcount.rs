@@ -0,0 +1,209 @@
+//! Reading how many times an animation should be played
+//!
+//! `image` doesn't expose this information, so it's read directly from the
+//! file. A missing or malformed field is treated as "play once". `0` means
+//! "forever" as in the file formats.
+
+/// Get the play count of a GIF image from the NETSCAPE2.0 application
+/// extension.
+pub fn gif(data: &[u8]) -> u32 {
+    read_gif(data).unwrap_or(1)
+}
+
+/// Get the play count of an APNG image from the `acTL` chunk.
+pub fn png(data: &[u8]) -> u32 {
+    read_png(data).unwrap_or(1)
+}
+
+/// Get the play count of a WebP image from the `ANIM` chunk.
+pub fn webp(data: &[u8]) -> u32 {
+    read_webp(data).unwrap_or(1)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    /// Read a GIF data sub-block. Returns an empty slice for a block
+    /// terminator.
+    fn sub_block(&mut self) -> Option<&'a [u8]> {
+        let len = self.byte()?;
+        self.take(len as usize)
+    }
+
+    /// Skip GIF data sub-blocks up to and including the block terminator.
+    fn skip_sub_blocks(&mut self) -> Option<()> {
+        while !self.sub_block()?.is_empty() {}
+        Some(())
+    }
+}
+
+fn read_gif(data: &[u8]) -> Option<u32> {
+    let mut reader = Reader(data);
+    if !reader.take(6)?.starts_with(b"GIF") {
+        return None;
+    }
+
+    // Logical screen descriptor and global color table
+    let flags = reader.take(7)?[4];
+    if flags & 0x80 != 0 {
+        reader.take(3 << ((flags & 7) + 1))?;
+    }
+
+    loop {
+        match reader.byte()? {
+            // Extension
+            0x21 => {
+                let label = reader.byte()?;
+                let first = reader.sub_block()?;
+                if first.is_empty() {
+                    continue;
+                }
+                if label == 0xff && (first == b"NETSCAPE2.0" || first == b"ANIMEXTS1.0") {
+                    let block = reader.sub_block()?;
+                    if let [1, lo, hi] = *block {
+                        // This is the number of repetitions after the first
+                        // play, which is how web browsers interpret it
+                        let count = u16::from_le_bytes([lo, hi]) as u32;
+                        return Some(if count == 0 { 0 } else { count + 1 });
+                    }
+                    if block.is_empty() {
+                        continue;
+                    }
+                }
+                reader.skip_sub_blocks()?;
+            }
+            // Image descriptor, local color table, and image data
+            0x2c => {
+                let flags = reader.take(9)?[8];
+                if flags & 0x80 != 0 {
+                    reader.take(3 << ((flags & 7) + 1))?;
+                }
+                reader.byte()?;
+                reader.skip_sub_blocks()?;
+            }
+            // Trailer
+            _ => return None,
+        }
+    }
+}
+
+fn read_png(data: &[u8]) -> Option<u32> {
+    let mut reader = Reader(data);
+    if reader.take(8)? != b"\x89PNG\r\n\x1a\n" {
+        return None;
+    }
+
+    loop {
+        let len = reader.take(4)?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]);
+        let ty = reader.take(4)?;
+        let body = reader.take(len as usize)?;
+        reader.take(4)?; // CRC
+        match ty {
+            b"acTL" if body.len() == 8 => {
+                return Some(u32::from_be_bytes([body[4], body[5], body[6], body[7]]))
+            }
+            // `acTL` must precede the image data
+            b"IDAT" => return None,
+            _ => {}
+        }
+    }
+}
+
+fn read_webp(data: &[u8]) -> Option<u32> {
+    let mut reader = Reader(data);
+    if reader.take(4)? != b"RIFF" {
+        return None;
+    }
+    reader.take(4)?;
+    if reader.take(4)? != b"WEBP" {
+        return None;
+    }
+
+    loop {
+        let fourcc = reader.take(4)?;
+        let len = reader.take(4)?;
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]);
+        let body = reader.take(len as usize)?;
+        if fourcc == b"ANIM" && body.len() >= 6 {
+            return Some(u16::from_le_bytes([body[4], body[5]]) as u32);
+        }
+        // Chunks are padded to even sizes
+        if len % 2 != 0 {
+            reader.take(1)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A GIF image with a global color table, the specified extension, and a
+    /// 1x1 image
+    fn gif_data(extension: &[u8]) -> Vec<u8> {
+        let mut data = b"GIF89a\x01\x00\x01\x00\x80\x00\x00".to_vec();
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(b"\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00");
+        data.extend_from_slice(b"\x02\x02\x44\x01\x00");
+        data.extend_from_slice(extension);
+        data.push(0x3b);
+        data
+    }
+
+    #[test]
+    fn gif_play_count() {
+        assert_eq!(gif(&gif_data(b"")), 1);
+        // Graphic control extension
+        assert_eq!(gif(&gif_data(b"\x21\xf9\x04\x00\x0a\x00\x00\x00")), 1);
+        assert_eq!(
+            gif(&gif_data(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")),
+            0
+        );
+        assert_eq!(
+            gif(&gif_data(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x02\x00\x00")),
+            3
+        );
+        assert_eq!(gif(b"GIF89a"), 1);
+    }
+
+    #[test]
+    fn png_play_count() {
+        let chunk = |ty: &[u8], body: &[u8]| {
+            let mut chunk = (body.len() as u32).to_be_bytes().to_vec();
+            chunk.extend_from_slice(ty);
+            chunk.extend_from_slice(body);
+            chunk.extend_from_slice(&[0; 4]);
+            chunk
+        };
+        let header = b"\x89PNG\r\n\x1a\n".to_vec();
+        let ihdr = chunk(b"IHDR", &[0; 13]);
+        let idat = chunk(b"IDAT", &[]);
+        let actl = chunk(b"acTL", &[0, 0, 0, 2, 0, 0, 0, 5]);
+        assert_eq!(png(&[&header[..], &ihdr, &actl, &idat].concat()), 5);
+        assert_eq!(png(&[&header[..], &ihdr, &idat, &actl].concat()), 1);
+        assert_eq!(png(&header), 1);
+    }
+
+    #[test]
+    fn webp_play_count() {
+        let mut data = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0".to_vec();
+        data.extend_from_slice(&[0; 10]);
+        data.extend_from_slice(b"ANIM\x06\0\0\0\0\0\0\0\x03\0");
+        assert_eq!(webp(&data), 3);
+        assert_eq!(webp(&data[..20]), 1);
+    }
+}
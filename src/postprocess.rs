@@ -0,0 +1,221 @@
+//! Output post-processing
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::mem::take;
+
+use crate::{Bmp2textOpts, Fragment, GlyphSet};
+
+/// Applies the output options of [`Bmp2textOpts`] to each output line.
+///
+/// Each line is passed along with a payload of type `T`, which is handed over
+/// to the callback with the line. If [`Bmp2textOpts::strip_blank_rows`] is
+/// enabled, blank lines are held back (with their payloads) until a non-blank
+/// line shows up, and the ones held back at the end are dropped. The payloads
+/// are borrowed so that the caller can reuse them; only the held-back ones
+/// are taken, leaving `T::default()`.
+///
+/// Whitespace and the glyph set's blank glyph (the glyph for an empty
+/// fragment, e.g., U+2800 for braille) both count as blank. The blank glyph
+/// doesn't if it also represents a non-empty fragment (e.g., with
+/// [`crate::BrailleBlank::Dot`]).
+pub(crate) struct LineFilter<'a, T> {
+    prefix: &'a str,
+    /// The blank glyph, or `""` if it's whitespace or ambiguous
+    blank: &'a str,
+    terminator: &'static str,
+    trim_trailing_whitespace: bool,
+    strip_blank_rows: bool,
+    /// A non-blank line has been emitted
+    started: bool,
+    held: Vec<(String, T)>,
+    buf: String,
+}
+
+impl<'a, T: Default> LineFilter<'a, T> {
+    pub fn new(opts: &Bmp2textOpts<'a>) -> Self {
+        Self {
+            prefix: opts.line_prefix,
+            blank: if opts.trim_trailing_whitespace || opts.strip_blank_rows {
+                distinct_blank_glyph(opts.glyph_set)
+            } else {
+                ""
+            },
+            terminator: opts.line_terminator.as_str(),
+            trim_trailing_whitespace: opts.trim_trailing_whitespace,
+            strip_blank_rows: opts.strip_blank_rows,
+            started: false,
+            held: Vec::new(),
+            buf: String::new(),
+        }
+    }
+
+    /// Disable the options that only make sense with plain text.
+    pub fn without_whitespace_processing(mut self) -> Self {
+        self.trim_trailing_whitespace = false;
+        self.strip_blank_rows = false;
+        self
+    }
+
+    /// Check if `glyph` is whitespace or the blank glyph.
+    pub fn is_blank_glyph(&self, glyph: &str) -> bool {
+        glyph.trim_end().is_empty() || (!self.blank.is_empty() && glyph == self.blank)
+    }
+
+    /// Remove trailing whitespace and blank glyphs from `line`.
+    fn trim_end<'b>(&self, mut line: &'b str) -> &'b str {
+        loop {
+            let trimmed = line.trim_end();
+            let trimmed = match trimmed.strip_suffix(self.blank) {
+                Some(x) if !self.blank.is_empty() => x,
+                _ => trimmed,
+            };
+            if trimmed.len() == line.len() {
+                return line;
+            }
+            line = trimmed;
+        }
+    }
+
+    /// Process `line`, which must not include a line terminator.
+    pub fn push<E>(
+        &mut self,
+        line: &str,
+        payload: &mut T,
+        f: &mut impl FnMut(&str, &T) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let trimmed = self.trim_end(line);
+        let blank = trimmed.is_empty();
+        let line = if self.trim_trailing_whitespace {
+            trimmed
+        } else {
+            line
+        };
+        self.push_trimmed(line, blank, payload, f)
+    }
+
+    /// Like [`Self::push`], but for a line that the caller has already
+    /// trimmed as specified by [`Bmp2textOpts::trim_trailing_whitespace`].
+    /// `blank` tells whether the line consists only of blank cells.
+    pub fn push_trimmed<E>(
+        &mut self,
+        line: &str,
+        blank: bool,
+        payload: &mut T,
+        f: &mut impl FnMut(&str, &T) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.strip_blank_rows && blank {
+            if self.started {
+                self.held.push((line.to_owned(), take(payload)));
+            }
+            return Ok(());
+        }
+        self.started = true;
+
+        if !self.held.is_empty() {
+            let mut held = take(&mut self.held);
+            for (line, payload) in held.drain(..) {
+                self.emit(&line, &payload, f)?;
+            }
+            // Reuse the allocation
+            self.held = held;
+        }
+
+        self.emit(line, payload, f)
+    }
+
+    fn emit<E>(
+        &mut self,
+        line: &str,
+        payload: &T,
+        f: &mut impl FnMut(&str, &T) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.buf.clear();
+        if self.trim_trailing_whitespace && line.is_empty() {
+            self.buf.push_str(self.prefix.trim_end());
+        } else {
+            self.buf.push_str(self.prefix);
+        }
+        self.buf.push_str(line);
+        self.buf.push_str(self.terminator);
+        f(&self.buf, payload)
+    }
+}
+
+/// Get the glyph for an empty fragment if it's neither whitespace nor the
+/// glyph for any other fragment. Returns `""` otherwise.
+fn distinct_blank_glyph(glyph_set: &dyn GlyphSet) -> &str {
+    let blank = glyph_set.fragment_to_glyph(0);
+    let [mask_w, mask_h] = glyph_set.mask_dims();
+    // `GlyphSetBuilder` limits masks to `MAX_MASK_PIXELS` (16) pixels
+    let num_fragments: Fragment = 1 << (mask_w * mask_h).min(16);
+    if blank.trim_end().is_empty()
+        || (1..num_fragments).any(|fragment| glyph_set.fragment_to_glyph(fragment) == blank)
+    {
+        ""
+    } else {
+        blank
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineTerminator;
+
+    fn filter(opts: &Bmp2textOpts, lines: &[&str]) -> String {
+        let mut filter = LineFilter::new(opts);
+        let mut out = String::new();
+        for line in lines {
+            filter
+                .push(line, &mut (), &mut |line, _| {
+                    out.push_str(line);
+                    Ok::<_, ()>(())
+                })
+                .unwrap();
+        }
+        out
+    }
+
+    const LINES: &[&str] = &["  ", " a  ", "", "  ", "b ", " ", ""];
+
+    #[test]
+    fn default() {
+        let opts = Bmp2textOpts::new();
+        assert_eq!(filter(&opts, LINES), "  \n a  \n\n  \nb \n \n\n");
+    }
+
+    #[test]
+    fn all_options() {
+        let mut opts = Bmp2textOpts::new();
+        opts.line_terminator = LineTerminator::CrLf;
+        opts.trim_trailing_whitespace = true;
+        opts.strip_blank_rows = true;
+        opts.line_prefix = "// ";
+        assert_eq!(filter(&opts, LINES), "//  a\r\n//\r\n//\r\n// b\r\n");
+    }
+
+    #[test]
+    fn strip_without_trim() {
+        let mut opts = Bmp2textOpts::new();
+        opts.strip_blank_rows = true;
+        assert_eq!(filter(&opts, LINES), " a  \n\n  \nb \n");
+    }
+
+    #[test]
+    fn blank_glyph() {
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = crate::GLYPH_SET_BRAILLE8;
+        opts.trim_trailing_whitespace = true;
+        opts.strip_blank_rows = true;
+        let lines = ["\u{2800}\u{2800}", "\u{2801}\u{2800} \u{2800}", "\u{2800}"];
+        assert_eq!(filter(&opts, &lines), "\u{2801}\n");
+
+        // `⠄` also represents the dot 3, so it's not blank
+        let glyph_set = crate::BrailleGlyphSet::eight_dot(crate::BrailleBlank::Dot);
+        opts.glyph_set = &glyph_set;
+        let lines = ["\u{2804}\u{2804}", "\u{2801}\u{2804} ", " "];
+        assert_eq!(
+            filter(&opts, &lines),
+            "\u{2804}\u{2804}\n\u{2801}\u{2804}\n"
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! Test helpers shared by the unit tests
+use crate::{set_spans_by_fn, ImageRead, Span};
+
+/// A pseudo-random image of dimensions `.0` with the density of `1 / .1`
+pub(crate) struct NoiseImage(pub [usize; 2], pub usize);
+
+impl ImageRead for NoiseImage {
+    fn dims(&self) -> [usize; 2] {
+        self.0
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        set_spans_by_fn(out, self.0[0], |x| {
+            ((x * 7919 + y * 104729).wrapping_mul(2654435761) >> 8) % self.1 == 0
+        });
+    }
+}
@@ -0,0 +1,199 @@
+//! Text-to-bitmap conversion (the reverse of [`crate::Bmp2text`])
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use crate::{set_spans_by_fn, Fragment, GlyphSet, ImageRead, Span};
+
+/// A bitmap image reconstructed by [`text2bmp`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    dims: [usize; 2],
+    pixels: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Get the dimensions of the image.
+    pub fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    /// Get the pixels in row-major order. `true` represents an "on" pixel.
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    /// Get the pixel at `[x, y]`.
+    pub fn get(&self, [x, y]: [usize; 2]) -> bool {
+        assert!(x < self.dims[0] && y < self.dims[1]);
+        self.pixels[x + y * self.dims[0]]
+    }
+}
+
+impl ImageRead for Bitmap {
+    fn dims(&self) -> [usize; 2] {
+        self.dims
+    }
+
+    fn copy_line_as_spans_to(&self, y: usize, out: &mut [Span]) {
+        let row = &self.pixels[y * self.dims[0]..][..self.dims[0]];
+        set_spans_by_fn(out, row.len(), |x| row[x]);
+    }
+}
+
+/// An error returned by [`text2bmp`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Text2bmpError {
+    /// The text contains a string that is not a glyph of the glyph set, or
+    /// a glyph that doesn't represent a specific pattern. `line` and `column`
+    /// are zero-based, and `column` is measured in `char`s.
+    UnknownGlyph { line: usize, column: usize },
+}
+
+impl fmt::Display for Text2bmpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownGlyph { line, column } => write!(
+                f,
+                "unknown glyph at line {}, column {}",
+                line + 1,
+                column + 1
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Text2bmpError {}
+
+/// Reconstruct a bitmap image from `text` produced with `glyph_set` by
+/// painting the mask of each glyph (see [`GlyphSet::glyph_to_mask`]).
+///
+/// Each line of `text` is a row of cells. Lines may end with `"\n"` or
+/// `"\r\n"`. Short lines are padded with off pixels.
+///
+/// If the glyph set has a non-zero mask overlap (e.g.,
+/// [`crate::GLYPH_SET_MS_2X3`]), each overlapping pixel is decided by a vote
+/// between the cells covering it: it's on if at least half of them say so.
+pub fn text2bmp(text: &str, glyph_set: &dyn GlyphSet) -> Result<Bitmap, Text2bmpError> {
+    let mask_dims = glyph_set.mask_dims();
+    let mask_overlap = glyph_set.mask_overlap();
+    let stride = [
+        mask_dims[0] - mask_overlap[0],
+        mask_dims[1] - mask_overlap[1],
+    ];
+
+    // Decode glyphs
+    let mut rows: Vec<Vec<Fragment>> = Vec::new();
+    for (line_i, line) in text.lines().enumerate() {
+        let mut row = Vec::new();
+        let mut rest = line;
+        let mut column = 0;
+        while !rest.is_empty() {
+            // Find the longest prefix that is a glyph
+            let max_len = glyph_set.max_glyph_len().min(rest.len());
+            let (len, mask) = (1..=max_len)
+                .rev()
+                .filter(|&len| rest.is_char_boundary(len))
+                .find_map(|len| Some((len, glyph_set.glyph_to_mask(&rest[..len])?)))
+                .ok_or(Text2bmpError::UnknownGlyph {
+                    line: line_i,
+                    column,
+                })?;
+            row.push(mask);
+            column += rest[..len].chars().count();
+            rest = &rest[len..];
+        }
+        rows.push(row);
+    }
+
+    let num_cells = [rows.iter().map(Vec::len).max().unwrap_or(0), rows.len()];
+    let dims = [
+        if num_cells[0] == 0 {
+            0
+        } else {
+            num_cells[0] * stride[0] + mask_overlap[0]
+        },
+        if num_cells[1] == 0 {
+            0
+        } else {
+            num_cells[1] * stride[1] + mask_overlap[1]
+        },
+    ];
+
+    // Count the votes for each pixel. Missing cells vote for off pixels.
+    let mut num_on = vec![0u8; dims[0] * dims[1]];
+    let mut num_votes = vec![0u8; dims[0] * dims[1]];
+    for (cell_y, row) in rows.iter().enumerate() {
+        for cell_x in 0..num_cells[0] {
+            let mask = row.get(cell_x).copied().unwrap_or(0);
+            for my in 0..mask_dims[1] {
+                for mx in 0..mask_dims[0] {
+                    let i = (cell_x * stride[0] + mx) + (cell_y * stride[1] + my) * dims[0];
+                    num_votes[i] += 1;
+                    num_on[i] += (mask >> (mx + my * mask_dims[0]) & 1) as u8;
+                }
+            }
+        }
+    }
+
+    let pixels = num_on
+        .iter()
+        .zip(num_votes.iter())
+        .map(|(&on, &votes)| on > 0 && on * 2 >= votes)
+        .collect();
+
+    Ok(Bitmap { dims, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bmp2text, Bmp2textOpts, GLYPH_SET_1X2, GLYPH_SET_BRAILLE8, GLYPH_SET_MS_2X3};
+
+    fn render(bitmap: &Bitmap) -> Vec<String> {
+        bitmap
+            .pixels()
+            .chunks(bitmap.dims()[0])
+            .map(|row| row.iter().map(|&on| if on { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "⣿⡇⠀⢸\n⠀⠉⠉⠁\n";
+        let bitmap = text2bmp(text, GLYPH_SET_BRAILLE8).unwrap();
+        assert_eq!(bitmap.dims(), [8, 8]);
+
+        let mut opts = Bmp2textOpts::new();
+        opts.glyph_set = GLYPH_SET_BRAILLE8;
+        let mut out = String::new();
+        Bmp2text::new()
+            .transform_and_write(&bitmap, &opts, &mut out)
+            .unwrap();
+        assert_eq!(out, text);
+    }
+
+    #[test]
+    fn short_line() {
+        let bitmap = text2bmp("▀▄\r\n█", GLYPH_SET_1X2).unwrap();
+        assert_eq!(render(&bitmap), ["#.", ".#", "#.", "#."]);
+    }
+
+    #[test]
+    fn overlap() {
+        // Cells of `GLYPH_SET_MS_2X3` are 2x3 pixels, each sharing a column
+        // and a row with its neighbors
+        let bitmap = text2bmp("█ \n  ", GLYPH_SET_MS_2X3).unwrap();
+        assert_eq!(bitmap.dims(), [3, 5]);
+        assert_eq!(render(&bitmap), ["##.", "##.", "#..", "...", "..."]);
+    }
+
+    #[test]
+    fn unknown_glyph() {
+        assert_eq!(
+            text2bmp("▀▄\n▀x", GLYPH_SET_1X2),
+            Err(Text2bmpError::UnknownGlyph { line: 1, column: 1 })
+        );
+    }
+}
@@ -89,6 +89,7 @@ impl Component for Model {
             (xform::InputTy::Wob, "White-on-black"),
             (xform::InputTy::Bow, "Black-on-white"),
             (xform::InputTy::EdgeCanny, "Detect edges"),
+            (xform::InputTy::Skeleton, "Thin strokes"),
         ];
         const STYLE_TABLE: &[(xform::Style, &str)] = &[
             (xform::Style::Slc, "SLC best effort"),
@@ -98,6 +99,11 @@ impl Component for Model {
             (xform::Style::_2x2, "Blocks 2x2"),
             (xform::Style::_2x3, "Blocks 2x3"),
             (xform::Style::Braille, "Braille patterns"),
+            (xform::Style::Braille6, "Braille patterns (six-dot)"),
+            (xform::Style::Ascii, "ASCII"),
+            (xform::Style::Line, "Box drawing"),
+            (xform::Style::LineHeavy, "Box drawing (heavy)"),
+            (xform::Style::LineDouble, "Box drawing (double)"),
         ];
 
         let ondrop = self.link.callback(|i| Msg::SetImage(i));
@@ -1,6 +1,6 @@
 use js_sys::global;
 use serde::{Deserialize, Serialize};
-use std::{convert::TryInto, future::Future, pin::Pin};
+use std::{future::Future, pin::Pin};
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
 
@@ -25,6 +25,8 @@ pub enum InputTy {
     Bow,
     /// Canny edge detection
     EdgeCanny,
+    /// Skeletonization
+    Skeleton,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
@@ -36,6 +38,11 @@ pub enum Style {
     _2x2,
     _2x3,
     Braille,
+    Braille6,
+    Ascii,
+    Line,
+    LineHeavy,
+    LineDouble,
 }
 
 impl Style {
@@ -48,6 +55,11 @@ impl Style {
             Self::_2x2 => img2text::GLYPH_SET_2X2,
             Self::_2x3 => img2text::GLYPH_SET_2X3,
             Self::Braille => img2text::GLYPH_SET_BRAILLE8,
+            Self::Braille6 => img2text::GLYPH_SET_BRAILLE6,
+            Self::Ascii => img2text::GLYPH_SET_ASCII,
+            Self::Line => img2text::GLYPH_SET_LINE,
+            Self::LineHeavy => img2text::GLYPH_SET_LINE_HEAVY,
+            Self::LineDouble => img2text::GLYPH_SET_LINE_DOUBLE,
         }
     }
 }
@@ -193,7 +205,7 @@ pub fn worker_kernel(
         image.pixels().map(|&image::Luma([luma])| luma),
     );
     log::trace!("histogram = {:?}", histogram);
-    let threshold = if let Some(x) = imageops::find_threshold(&histogram) {
+    let mut threshold = if let Some(x) = imageops::find_threshold(&histogram) {
         log::debug!("threshold = {}", x);
         x
     } else {
@@ -233,17 +245,23 @@ pub fn worker_kernel(
             }
             false
         }
+        InputTy::Skeleton => {
+            imageops::binarize_and_skeletonize(&mut image, threshold);
+            threshold = 128;
+            false
+        }
     };
 
     // bmp2text options
     let b2t_opts = shared_opts.to_b2t_opts();
 
     use img2text::ImageRead;
-    let img_proxy = GrayImageRead {
-        image: &image,
-        threshold,
-        invert,
-    };
+    let mut img_proxy = img2text::image::GrayImageRead::new(
+        image.as_raw(),
+        [image.width() as usize, image.height() as usize],
+    );
+    img_proxy.threshold = threshold as u8;
+    img_proxy.invert = invert;
     let max_out_len =
         if let Some(x) = img2text::max_output_len_for_image_dims(img_proxy.dims(), &b2t_opts) {
             x
@@ -271,27 +289,3 @@ fn create_canvas() -> HtmlCanvasElement {
         .unwrap()
         .unchecked_into::<HtmlCanvasElement>()
 }
-
-struct GrayImageRead<'a> {
-    image: &'a image::GrayImage,
-    threshold: usize,
-    invert: bool,
-}
-
-impl img2text::ImageRead for GrayImageRead<'_> {
-    fn dims(&self) -> [usize; 2] {
-        let (w, h) = self.image.dimensions();
-        [w.try_into().unwrap(), h.try_into().unwrap()]
-    }
-
-    fn copy_line_as_spans_to(&self, y: usize, out: &mut [img2text::Span]) {
-        let Self {
-            image,
-            threshold,
-            invert,
-        } = *self;
-        img2text::set_spans_by_fn(out, self.dims()[0], move |x| {
-            (image[(x as u32, y as u32)].0[0] as usize >= threshold) ^ invert
-        });
-    }
-}